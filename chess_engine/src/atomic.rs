@@ -0,0 +1,47 @@
+use crate::*;
+
+impl Board {
+    // The side whose king was blown up, which has lost in atomic chess.
+    pub(crate) fn exploded_king(&self) -> Option<Team> {
+        if !self.config.atomic {
+            return None;
+        }
+
+        [Team::White, Team::Black]
+            .iter()
+            .find(|team| {
+                self.enumerate_pieces(|piece, _| piece.necessity && piece.team == **team)
+                    .is_empty()
+            })
+            .copied()
+    }
+
+    // Check in atomic chess. Kings capture nothing, so they give no check,
+    // and kings standing next to each other are safe since taking one would
+    // blow up the other. A side without a king counts as in check, one that
+    // just blew up the enemy king does not.
+    pub(crate) fn atomic_check(&self, team: Team) -> bool {
+        let kings = self.enumerate_pieces(|piece, _| piece.necessity && piece.team == team);
+        let enemies = self.enumerate_pieces(|piece, _| piece.necessity && piece.team != team);
+        if kings.is_empty() {
+            return true;
+        }
+
+        if enemies.is_empty() {
+            return false;
+        }
+
+        let touching = |(ax, ay): Point, (bx, by): Point| {
+            (ax as i32 - bx as i32).abs() <= 1 && (ay as i32 - by as i32).abs() <= 1
+        };
+        if kings
+            .iter()
+            .any(|king| enemies.iter().any(|enemy| touching(*king, *enemy)))
+        {
+            return false;
+        }
+
+        let threat = self.get_threatened(team);
+        kings.iter().any(|(x, y)| threat[*x][*y])
+    }
+}
@@ -0,0 +1,98 @@
+use chess_engine::search::*;
+use chess_engine::variant::Variant;
+use chess_engine::Board;
+
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+// Positions searched for each thread count, from the opening, middlegame
+// and endgame so one kind of position doesn't decide the result.
+const POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r2q1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 9",
+    "2r3k1/pp3ppp/4p3/3n4/3P4/P4N2/1P3PPP/2R3K1 w - - 0 25",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/8/2K5/3P4/8/8 w - - 0 1",
+];
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+
+    // With `--variant <file>` only the variant's start position is searched.
+    let boards: Vec<Board> = match args.iter().position(|arg| arg == "--variant") {
+        Some(i) => {
+            let path = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+
+            let variant = Variant::load(&path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            println!("variant {}", variant.name);
+            vec![variant.board()]
+        }
+        None => POSITIONS
+            .iter()
+            .map(|fen| Board::from_fen(fen, None).expect("invalid bench position"))
+            .collect(),
+    };
+
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let depth = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(3);
+    let max_threads = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(cores);
+
+    println!("cores {}", cores);
+    if max_threads > cores {
+        eprintln!(
+            "warning: {} threads on {} cores, the speedup above {} threads is not meaningful",
+            max_threads, cores, cores
+        );
+    }
+
+    // The speedup is in the mean time to reach the depth, which is what
+    // more threads buy. Nodes per second grows with threads even when they
+    // only search the same nodes again.
+    println!("depth {}, {} positions", depth, boards.len());
+    println!(
+        "{:>8} {:>10} {:>12} {:>10} {:>8}",
+        "threads", "mean (ms)", "mean nodes", "nps", "speedup"
+    );
+
+    let mut baseline = None;
+    let mut threads = 1;
+    while threads <= max_threads {
+        let config = SearchConfig {
+            depth,
+            threads,
+            ..SearchConfig::default()
+        };
+
+        let mut elapsed = 0.0;
+        let mut nodes = 0;
+        for board in boards.iter() {
+            let start = Instant::now();
+            nodes += search(board, &config).nodes;
+            elapsed += start.elapsed().as_secs_f64();
+        }
+
+        let count = boards.len() as f64;
+        let mean = elapsed / count;
+        let baseline = *baseline.get_or_insert(mean);
+
+        println!(
+            "{:>8} {:>10.1} {:>12.0} {:>10.0} {:>8.2}",
+            threads,
+            mean * 1000.0,
+            nodes as f64 / count,
+            nodes as f64 / elapsed,
+            baseline / mean
+        );
+
+        threads *= 2;
+    }
+}
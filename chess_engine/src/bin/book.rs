@@ -0,0 +1,83 @@
+use chess_engine::notation::*;
+use chess_engine::pgn::parse_pgn;
+use chess_engine::polyglot::Book;
+use chess_engine::Board;
+
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "usage:
+    book build <games.pgn> <book.bin> [--min-games N] [--max-ply N]
+    book show <book.bin> [SAN moves...]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn option(args: &[String], name: &str, default: usize) -> usize {
+    match args.iter().position(|arg| arg == name) {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| fail(&format!("{} needs a number", name))),
+        None => default,
+    }
+}
+
+fn build(args: &[String]) {
+    if args.len() < 2 {
+        fail(USAGE);
+    }
+
+    let text = fs::read_to_string(&args[0]).unwrap_or_else(|e| fail(&e.to_string()));
+    let games = parse_pgn(&text);
+    let book = Book::build(
+        &games,
+        option(args, "--min-games", 1) as u32,
+        option(args, "--max-ply", 20),
+    );
+
+    book.save(&args[1]).unwrap_or_else(|e| fail(&e.to_string()));
+    println!(
+        "{} games, {} book entries written to {}",
+        games.len(),
+        book.entries.len(),
+        args[1]
+    );
+}
+
+fn show(args: &[String]) {
+    if args.is_empty() {
+        fail(USAGE);
+    }
+
+    let book = Book::load(&args[0]).unwrap_or_else(|e| fail(&e.to_string()));
+    let mut board = Board::new(None);
+
+    for san in args[1..].iter() {
+        let ply =
+            parse_san(&mut board, san).unwrap_or_else(|| fail(&format!("illegal move {}", san)));
+        board.play(&ply);
+    }
+
+    for (ply, weight) in book.moves(&board) {
+        println!(
+            "{}{} {}",
+            square_name(ply.from),
+            square_name(ply.to),
+            weight
+        );
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("build") => build(&args[1..]),
+        Some("show") => show(&args[1..]),
+        _ => fail(USAGE),
+    }
+}
@@ -0,0 +1,112 @@
+use chess_engine::database::{Database, Query};
+use chess_engine::notation::*;
+use chess_engine::Board;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage:
+    database import <games.db> <games.pgn>...
+    database search <games.db> [--player NAME] [--white NAME] [--black NAME]
+        [--from DATE] [--to DATE] [--result RESULT] [--eco CODE]
+        [--material SIGNATURE] [--moves \"e4 e5 Nf3\"]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn option(args: &[String], name: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == name)?;
+    Some(
+        args.get(i + 1)
+            .cloned()
+            .unwrap_or_else(|| fail(&format!("{} needs a value", name))),
+    )
+}
+
+fn import(args: &[String]) {
+    if args.len() < 2 {
+        fail(USAGE);
+    }
+
+    let mut database = if Path::new(&args[0]).exists() {
+        Database::load(&args[0]).unwrap_or_else(|e| fail(&e.to_string()))
+    } else {
+        Database::new()
+    };
+
+    for path in args[1..].iter() {
+        let added = database
+            .import(path)
+            .unwrap_or_else(|e| fail(&e.to_string()));
+        println!("{} games from {}", added, path);
+    }
+
+    database
+        .save(&args[0])
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    println!("{} games in {}", database.len(), args[0]);
+}
+
+fn search(args: &[String]) {
+    if args.is_empty() {
+        fail(USAGE);
+    }
+
+    let database = Database::load(&args[0]).unwrap_or_else(|e| fail(&e.to_string()));
+
+    // The position is the one reached by the moves from the start.
+    let position = option(args, "--moves").map(|moves| {
+        let mut board = Board::new(None);
+        for san in moves.split_whitespace() {
+            let ply = parse_san(&mut board, san)
+                .unwrap_or_else(|| fail(&format!("illegal move {}", san)));
+            board.play(&ply);
+        }
+
+        board.polyglot_key()
+    });
+
+    let query = Query {
+        player: option(args, "--player"),
+        white: option(args, "--white"),
+        black: option(args, "--black"),
+        date_from: option(args, "--from"),
+        date_to: option(args, "--to"),
+        result: option(args, "--result"),
+        eco: option(args, "--eco"),
+        position,
+        material: option(args, "--material"),
+    };
+
+    for id in database.search(&query) {
+        let tags = database.tags(id).unwrap_or(&[]);
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag == name)
+                .map_or("?", |(_, value)| value.as_str())
+        };
+
+        println!(
+            "{:>6} {} - {} {} {} {}",
+            id,
+            tag("White"),
+            tag("Black"),
+            tag("Result"),
+            tag("Date"),
+            tag("ECO")
+        );
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("import") => import(&args[1..]),
+        Some("search") => search(&args[1..]),
+        _ => fail(USAGE),
+    }
+}
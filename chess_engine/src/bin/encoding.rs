@@ -0,0 +1,77 @@
+use chess_engine::binary;
+use chess_engine::pgn::parse_pgn;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: encoding <games.pgn>";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// Compares the binary encoding with PGN for the games in a file: the size
+// of each, and the time to read the games back onto boards.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let path = args.get(1).unwrap_or_else(|| fail(USAGE));
+    let text = fs::read_to_string(path).unwrap_or_else(|e| fail(&e.to_string()));
+
+    let start = Instant::now();
+    let games = parse_pgn(&text);
+    let boards: Vec<_> = games
+        .iter()
+        .map(|game| game.replay(game.moves.len()).0)
+        .collect();
+    let pgn_time = start.elapsed();
+
+    let start = Instant::now();
+    let encoded: Vec<Vec<u8>> = games
+        .iter()
+        .zip(boards.iter())
+        .map(|(game, board)| binary::encode(board, &game.tags))
+        .collect();
+    let encode_time = start.elapsed();
+
+    let start = Instant::now();
+    for bytes in encoded.iter() {
+        binary::decode(bytes, None).unwrap_or_else(|| fail("a game did not decode"));
+    }
+    let decode_time = start.elapsed();
+
+    let moves: usize = boards.iter().map(|board| board.history.len()).sum();
+    let size: usize = encoded.iter().map(|bytes| bytes.len()).sum();
+    // Without the tags, which take the same room in either format.
+    let untagged: usize = boards
+        .iter()
+        .map(|board| binary::encode(board, &[]).len())
+        .sum();
+
+    println!("{} games, {} moves", games.len(), moves);
+    println!("{:>8} {:>12} {:>12}", "", "bytes", "read (ms)");
+    println!(
+        "{:>8} {:>12} {:>12.1}",
+        "pgn",
+        text.len(),
+        pgn_time.as_secs_f64() * 1000.0
+    );
+    println!(
+        "{:>8} {:>12} {:>12.1}",
+        "binary",
+        size,
+        decode_time.as_secs_f64() * 1000.0
+    );
+    println!(
+        "binary is {:.1}% of the pgn size, encoding took {:.1} ms",
+        100.0 * size as f64 / text.len().max(1) as f64,
+        encode_time.as_secs_f64() * 1000.0
+    );
+    println!(
+        "without tags {} bytes, {:.2} bytes per move",
+        untagged,
+        untagged as f64 / moves.max(1) as f64
+    );
+}
@@ -0,0 +1,28 @@
+use chess_engine::tablebase::generate::generate;
+
+use std::env;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: tablebase <directory> <material>...
+    e.g. tablebase tables KQvK KRvK KPvK KBNvK";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+
+    let directory = Path::new(&args[0]);
+    for material in args[1..].iter() {
+        let start = Instant::now();
+        if let Err(e) = generate(directory, material) {
+            eprintln!("{}: {}", material, e);
+            process::exit(1);
+        }
+
+        println!("{} done in {:.1}s", material, start.elapsed().as_secs_f64());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::*;
+use result::{GameResult, Termination};
+
+// A compact encoding of whole games, for storing a great many of them:
+//
+//   tags       count, then name and value of each as length and UTF-8
+//   result     0 for none, else 1 + the termination, then the winner as
+//              0 for none, 1 for white and 2 for black
+//   start      FEN of the starting position, empty for the usual one
+//   moves      count, then each move as its index in the sorted list of
+//              legal moves, in just as many bits as that list needs
+//
+// Counts and lengths are LEB128 varints. A standard game never needs more
+// than a byte per move and usually around six bits.
+
+const TERMINATIONS: [Termination; 10] = [
+    Termination::Checkmate,
+    Termination::Stalemate,
+    Termination::Resignation,
+    Termination::Timeout,
+    Termination::Agreement,
+    Termination::Repetition,
+    Termination::FiftyMoves,
+    Termination::InsufficientMaterial,
+    Termination::Abandonment,
+    Termination::Variant,
+];
+
+// Bits needed to tell `count` choices apart, none for a forced move.
+fn bits_for(count: usize) -> u32 {
+    if count <= 1 {
+        0
+    } else {
+        usize::BITS - (count - 1).leading_zeros()
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+    bits: u32,
+}
+
+impl Writer {
+    fn varint(&mut self, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        self.varint(text.len());
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+
+    // Packs `count` bits of `value` after the ones already written, most
+    // significant first.
+    fn bits(&mut self, value: usize, count: u32) {
+        for i in (0..count).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+
+            let bit = (value >> i & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+            self.bits += 1;
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    bits: u32,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.position)?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn varint(&mut self) -> Option<usize> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.varint()?;
+        let bytes = self.bytes.get(self.position..self.position + length)?;
+        self.position += length;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    fn bits(&mut self, count: u32) -> Option<usize> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = *self.bytes.get(self.position + (self.bits / 8) as usize)?;
+            let bit = byte >> (7 - self.bits % 8) & 1;
+            value = value << 1 | bit as usize;
+            self.bits += 1;
+        }
+
+        Some(value)
+    }
+}
+
+// The game on `board`, from where its history starts, with `tags` in the
+// header.
+pub fn encode(board: &Board, tags: &[(String, String)]) -> Vec<u8> {
+    let mut writer = Writer {
+        bytes: Vec::new(),
+        bits: 0,
+    };
+
+    writer.varint(tags.len());
+    for (name, value) in tags {
+        writer.string(name);
+        writer.string(value);
+    }
+
+    match board.result {
+        Some(result) => {
+            let termination = TERMINATIONS
+                .iter()
+                .position(|termination| *termination == result.termination)
+                .unwrap();
+            writer.bytes.push(1 + termination as u8);
+            writer.bytes.push(match result.winner {
+                None => 0,
+                Some(Team::White) => 1,
+                Some(Team::Black) => 2,
+            });
+        }
+        None => writer.bytes.extend_from_slice(&[0, 0]),
+    }
+
+    let (start, moves) = board.replay_history();
+    let fen = start.to_fen();
+    if fen == Board::new(Some(board.config.clone())).to_fen() {
+        writer.string("");
+    } else {
+        writer.string(&fen);
+    }
+
+    writer.varint(moves.len());
+    for (index, legal) in moves {
+        writer.bits(index, bits_for(legal.len()));
+    }
+
+    writer.bytes
+}
+
+fn read_tags(reader: &mut Reader) -> Option<Vec<(String, String)>> {
+    let mut tags = Vec::new();
+    for _ in 0..reader.varint()? {
+        tags.push((reader.string()?, reader.string()?));
+    }
+
+    Some(tags)
+}
+
+// Only the tags of an encoded game, without playing through its moves.
+pub fn decode_tags(bytes: &[u8]) -> Option<Vec<(String, String)>> {
+    read_tags(&mut Reader {
+        bytes,
+        position: 0,
+        bits: 0,
+    })
+}
+
+// The board and tags of an encoded game, played with the rules of
+// `configuration`.
+pub fn decode(
+    bytes: &[u8],
+    configuration: Option<BoardConfig>,
+) -> Option<(Board, Vec<(String, String)>)> {
+    let mut reader = Reader {
+        bytes,
+        position: 0,
+        bits: 0,
+    };
+
+    let tags = read_tags(&mut reader)?;
+    let termination = reader.byte()?;
+    let winner = match reader.byte()? {
+        0 => None,
+        1 => Some(Team::White),
+        2 => Some(Team::Black),
+        _ => return None,
+    };
+    let result = match termination {
+        0 => None,
+        t => Some(GameResult {
+            winner,
+            termination: *TERMINATIONS.get(t as usize - 1)?,
+        }),
+    };
+
+    let fen = reader.string()?;
+    let mut board = if fen.is_empty() {
+        Board::new(configuration)
+    } else {
+        Board::from_fen(&fen, configuration)?
+    };
+
+    for _ in 0..reader.varint()? {
+        let legal = board.get_legal_moves();
+        let index = reader.bits(bits_for(legal.len()))?;
+        search::make(&mut board, legal.get(index)?);
+    }
+
+    board.check = board.check_check(board.current_player);
+    board.finished = result.is_some();
+    board.winner = winner;
+    board.result = result;
+
+    Some((board, tags))
+}
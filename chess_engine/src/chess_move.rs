@@ -1,7 +1,8 @@
 use crate::*;
+use record::{MoveRecord, PieceRecord};
 use std::mem::replace;
 
-pub trait ChessMove {
+pub trait ChessMove: Send {
     fn get_affected_tiles(&self) -> Vec<Point>;
     fn get_target_tile(&self) -> Point;
 
@@ -15,6 +16,65 @@ pub trait ChessMove {
     fn as_regular(&self) -> Option<&RegularMove> {
         None
     }
+
+    fn as_drop(&self) -> Option<&DropMove> {
+        None
+    }
+
+    // The piece taken off the board, once the move has been performed.
+    fn captured(&self) -> Option<&Piece> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn ChessMove>;
+
+    // The move as plain data, see `record::MoveRecord::restore`.
+    fn record(&self) -> MoveRecord;
+}
+
+impl Clone for Box<dyn ChessMove> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// A move described only by its endpoints, independent of any board state.
+// A drop has `from` equal to `to` and names the piece taken from the pocket.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Ply {
+    pub from: Point,
+    pub to: Point,
+    pub promotion: Option<String>,
+    pub drop: Option<String>,
+}
+
+impl Ply {
+    pub fn new(from: Point, to: Point) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        }
+    }
+
+    pub fn promoting(from: Point, to: Point, into: &str) -> Self {
+        Self {
+            from,
+            to,
+            promotion: Some(into.to_owned()),
+            drop: None,
+        }
+    }
+
+    pub fn dropping(name: &str, to: Point) -> Self {
+        Self {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(name.to_owned()),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -22,14 +82,14 @@ pub struct RegularMove {
     pub from: Point,
     pub to: Point,
     pub prev_state: Option<Piece>,
-    killed: Option<Piece>,
+    pub(crate) killed: Option<Piece>,
 }
 
 impl RegularMove {
     pub fn new(from: Point, to: Point) -> Self {
         Self {
-            from: from,
-            to: to,
+            from,
+            to,
             prev_state: None,
             killed: None,
         }
@@ -41,6 +101,18 @@ impl ChessMove for RegularMove {
         Some(self)
     }
 
+    fn captured(&self) -> Option<&Piece> {
+        self.killed.as_ref()
+    }
+
+    fn clone_box(&self) -> Box<dyn ChessMove> {
+        Box::new(self.clone())
+    }
+
+    fn record(&self) -> MoveRecord {
+        MoveRecord::regular(self)
+    }
+
     fn get_affected_tiles(&self) -> Vec<Point> {
         vec![self.from, self.to]
     }
@@ -49,7 +121,7 @@ impl ChessMove for RegularMove {
         self.to
     }
 
-    fn perform(&mut self, mut tiles: &mut BoardCollection) {
+    fn perform(&mut self, tiles: &mut BoardCollection) {
         self.prev_state = tiles[self.from.0][self.from.1].clone();
 
         let mut next = self.prev_state.clone();
@@ -65,25 +137,26 @@ impl ChessMove for RegularMove {
         tiles[self.from.0][self.from.1] = None;
     }
 
-    fn reverse(&mut self, mut tiles: &mut BoardCollection) {
+    fn reverse(&mut self, tiles: &mut BoardCollection) {
         tiles[self.from.0][self.from.1] = self.prev_state.clone();
 
         tiles[self.to.0][self.to.1] = self.killed.clone();
     }
 }
 
+#[derive(Clone)]
 pub struct EnPassant {
-    from: Point,
-    to: Point,
-    killpos: Point,
-    killed: Option<Piece>,
+    pub(crate) from: Point,
+    pub(crate) to: Point,
+    pub(crate) killpos: Point,
+    pub(crate) killed: Option<Piece>,
 }
 
 impl EnPassant {
     pub fn new(from: Point, to: Point, kill: Point) -> Self {
         Self {
-            from: from,
-            to: to,
+            from,
+            to,
             killpos: kill,
             killed: None,
         }
@@ -91,6 +164,18 @@ impl EnPassant {
 }
 
 impl ChessMove for EnPassant {
+    fn captured(&self) -> Option<&Piece> {
+        self.killed.as_ref()
+    }
+
+    fn clone_box(&self) -> Box<dyn ChessMove> {
+        Box::new(self.clone())
+    }
+
+    fn record(&self) -> MoveRecord {
+        MoveRecord::en_passant(self)
+    }
+
     fn get_affected_tiles(&self) -> Vec<Point> {
         vec![self.from, self.to, self.killpos]
     }
@@ -101,42 +186,86 @@ impl ChessMove for EnPassant {
 
     fn perform(&mut self, tiles: &mut BoardCollection) {
         let piece = tiles[self.from.0][self.from.1].clone();
-        self.killed = replace(&mut tiles[self.killpos.0][self.killpos.1], None);
+        self.killed = tiles[self.killpos.0][self.killpos.1].take();
 
         tiles[self.to.0][self.to.1] = piece;
         tiles[self.from.0][self.from.1] = None;
     }
 
     fn reverse(&mut self, tiles: &mut BoardCollection) {
-        let piece = replace(&mut tiles[self.to.0][self.to.1], None);
+        let piece = tiles[self.to.0][self.to.1].take();
         tiles[self.from.0][self.from.1] = piece;
 
         tiles[self.killpos.0][self.killpos.1] = self.killed.clone();
     }
 }
 
+#[derive(Clone)]
 pub struct Castling {
     pub rook_from: Point,
     pub rook_to: Point,
     pub king_from: Point,
     pub king_to: Point,
+    pub(crate) king: Option<Piece>,
+    pub(crate) rook: Option<Piece>,
 }
 
 impl Castling {
-    pub fn new(rook: Point, king: Point) -> Self {
-        let rook_x = if rook.0 == 0 { 3 } else { 5 };
-        let king_x = if rook.0 == 0 { 2 } else { 6 };
+    // The king always lands on the third file from its side of a board
+    // `width` files wide, the c- or g-file normally, with the rook next to it
+    // on the inside, wherever the two started out, as in Chess960.
+    pub fn new(rook: Point, king: Point, width: usize) -> Self {
+        let long = rook.0 < king.0;
+        let rook_x = if long { 3 } else { width - 3 };
+        let king_x = if long { 2 } else { width - 2 };
 
         Self {
             rook_from: rook,
             king_from: king,
             rook_to: (rook_x, rook.1),
             king_to: (king_x, king.1),
+            king: None,
+            rook: None,
         }
     }
+
+    pub fn is_long(&self) -> bool {
+        self.rook_from.0 < self.king_from.0
+    }
+
+    // Every tile both pieces pass over or land on, which has to be empty
+    // apart from the king and rook themselves.
+    pub fn get_path(&self) -> Vec<Point> {
+        let files = [
+            self.rook_from.0,
+            self.rook_to.0,
+            self.king_from.0,
+            self.king_to.0,
+        ];
+        let min = *files.iter().min().unwrap();
+        let max = *files.iter().max().unwrap();
+
+        (min..=max).map(|x| (x, self.king_from.1)).collect()
+    }
+
+    // Tiles the king stands on or crosses, none of which may be attacked.
+    pub fn get_king_path(&self) -> Vec<Point> {
+        let from = self.king_from.0.min(self.king_to.0);
+        let to = self.king_from.0.max(self.king_to.0);
+
+        (from..=to).map(|x| (x, self.king_from.1)).collect()
+    }
 }
 
 impl ChessMove for Castling {
+    fn clone_box(&self) -> Box<dyn ChessMove> {
+        Box::new(self.clone())
+    }
+
+    fn record(&self) -> MoveRecord {
+        MoveRecord::castling(self)
+    }
+
     fn get_affected_tiles(&self) -> Vec<Point> {
         vec![self.rook_from, self.rook_to, self.king_from, self.king_to]
     }
@@ -149,27 +278,167 @@ impl ChessMove for Castling {
         self.king_to
     }
 
+    // Both pieces are lifted before either is put down, since in Chess960
+    // the king may land where the rook stood or the other way around.
     fn perform(&mut self, tiles: &mut BoardCollection) {
-        let king = tiles[self.king_from.0][self.king_from.1].clone();
-        tiles[self.king_to.0][self.king_to.1] = king;
+        self.king = tiles[self.king_from.0][self.king_from.1].take();
+        self.rook = tiles[self.rook_from.0][self.rook_from.1].take();
+
+        let moved = |piece: &Option<Piece>| {
+            piece.clone().map(|mut piece| {
+                piece.has_moved = true;
+                piece
+            })
+        };
+
+        tiles[self.king_to.0][self.king_to.1] = moved(&self.king);
+        tiles[self.rook_to.0][self.rook_to.1] = moved(&self.rook);
+    }
+
+    fn reverse(&mut self, tiles: &mut BoardCollection) {
+        tiles[self.king_to.0][self.king_to.1] = None;
+        tiles[self.rook_to.0][self.rook_to.1] = None;
+
+        tiles[self.king_from.0][self.king_from.1] = self.king.clone();
+        tiles[self.rook_from.0][self.rook_from.1] = self.rook.clone();
+    }
+}
+
+// Puts a piece from the mover's pocket on an empty tile, as in Crazyhouse.
+#[derive(Clone)]
+pub struct DropMove {
+    pub piece: Piece,
+    pub to: Point,
+}
+
+impl DropMove {
+    pub fn new(piece: Piece, to: Point) -> Self {
+        Self { piece, to }
+    }
+}
+
+impl ChessMove for DropMove {
+    fn as_drop(&self) -> Option<&Self> {
+        Some(self)
+    }
 
-        tiles[self.king_from.0][self.king_from.1] = None;
+    fn clone_box(&self) -> Box<dyn ChessMove> {
+        Box::new(self.clone())
+    }
 
-        let rook = tiles[self.rook_from.0][self.rook_from.1].clone();
-        tiles[self.rook_to.0][self.rook_to.1] = rook;
+    fn record(&self) -> MoveRecord {
+        MoveRecord::Drop {
+            piece: PieceRecord::of(&self.piece),
+            to: self.to,
+        }
+    }
 
-        tiles[self.rook_from.0][self.rook_from.1] = None;
+    fn get_affected_tiles(&self) -> Vec<Point> {
+        vec![self.to]
+    }
+
+    fn get_target_tile(&self) -> Point {
+        self.to
+    }
+
+    fn can_kill(&self) -> bool {
+        false
+    }
+
+    fn perform(&mut self, tiles: &mut BoardCollection) {
+        tiles[self.to.0][self.to.1] = Some(self.piece.clone());
     }
 
     fn reverse(&mut self, tiles: &mut BoardCollection) {
-        let king = tiles[self.king_to.0][self.king_to.1].clone();
-        tiles[self.king_from.0][self.king_from.1] = king;
+        tiles[self.to.0][self.to.1] = None;
+    }
+}
 
-        tiles[self.king_to.0][self.king_to.1] = None;
+// A capture in atomic chess. The capturer and every piece but pawns around
+// the captured one are blown off the board, and kept here for `reverse`.
+#[derive(Clone)]
+pub struct Explosion {
+    pub inner: Box<dyn ChessMove>,
+    pub exploded: Vec<(Point, Piece)>,
+}
 
-        let rook = tiles[self.rook_to.0][self.rook_to.1].clone();
-        tiles[self.rook_from.0][self.rook_from.1] = rook;
+impl Explosion {
+    pub fn new(inner: Box<dyn ChessMove>) -> Self {
+        Self {
+            inner,
+            exploded: Vec::new(),
+        }
+    }
+}
 
-        tiles[self.rook_to.0][self.rook_to.1] = None;
+impl ChessMove for Explosion {
+    fn as_regular(&self) -> Option<&RegularMove> {
+        self.inner.as_regular()
+    }
+
+    fn captured(&self) -> Option<&Piece> {
+        self.inner.captured()
+    }
+
+    fn clone_box(&self) -> Box<dyn ChessMove> {
+        Box::new(self.clone())
+    }
+
+    fn record(&self) -> MoveRecord {
+        MoveRecord::Explosion {
+            inner: Box::new(self.inner.record()),
+            exploded: self
+                .exploded
+                .iter()
+                .map(|(point, piece)| (*point, PieceRecord::of(piece)))
+                .collect(),
+        }
+    }
+
+    fn get_affected_tiles(&self) -> Vec<Point> {
+        self.inner.get_affected_tiles()
+    }
+
+    fn get_target_tile(&self) -> Point {
+        self.inner.get_target_tile()
+    }
+
+    fn perform(&mut self, tiles: &mut BoardCollection) {
+        self.exploded.clear();
+        self.inner.perform(tiles);
+        if self.inner.captured().is_none() {
+            return;
+        }
+
+        let (cx, cy) = self.inner.get_target_tile();
+        let (width, height) = (tiles.len() as i32, tiles[0].len() as i32);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    continue;
+                }
+
+                let (x, y) = (x as usize, y as usize);
+                let centre = dx == 0 && dy == 0;
+                let blown = match tiles[x][y].as_ref() {
+                    Some(piece) => centre || piece.name != "Pawn",
+                    None => false,
+                };
+
+                if blown {
+                    self.exploded.push(((x, y), tiles[x][y].take().unwrap()));
+                }
+            }
+        }
+    }
+
+    fn reverse(&mut self, tiles: &mut BoardCollection) {
+        for ((x, y), piece) in self.exploded.drain(..) {
+            tiles[x][y] = Some(piece);
+        }
+
+        self.inner.reverse(tiles);
     }
 }
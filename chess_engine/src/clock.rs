@@ -0,0 +1,357 @@
+use crate::*;
+use result::Termination;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Where the clock reads the time, so tests can move it by hand.
+pub trait TimeSource: Send + Sync {
+    // Time since some fixed point, never going backwards.
+    fn now(&self) -> Duration;
+}
+
+pub struct RealTime {
+    start: Instant,
+}
+
+impl RealTime {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for RealTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// Time that only moves when told to. Clones share the same time.
+#[derive(Clone, Default)]
+pub struct ManualTime {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+// What a side gets back for each move it makes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Bonus {
+    None,
+    // Fischer: a fixed amount after every move.
+    Increment(Duration),
+    // Bronstein: the time the move took, up to the delay.
+    Delay(Duration),
+}
+
+// `time` for `moves` moves, or for the rest of the game without a count.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub bonus: Bonus,
+}
+
+// The stages of a game in order. Each stage's time is added when the one
+// before it is done, and the last stage repeats if it has a move count.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    pub fn new(stages: Vec<Stage>) -> Self {
+        Self { stages }
+    }
+
+    pub fn sudden_death(time: Duration) -> Self {
+        Self::with_bonus(time, Bonus::None)
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> Self {
+        Self::with_bonus(time, Bonus::Increment(increment))
+    }
+
+    pub fn bronstein(time: Duration, delay: Duration) -> Self {
+        Self::with_bonus(time, Bonus::Delay(delay))
+    }
+
+    fn with_bonus(time: Duration, bonus: Bonus) -> Self {
+        Self::new(vec![Stage {
+            moves: None,
+            time,
+            bonus,
+        }])
+    }
+
+    // Reads a PGN TimeControl tag such as "300+2" or "40/5400+30:1800+30",
+    // stages being "moves/seconds" or just "seconds" with an optional
+    // "+increment". A "d" before the increment, as in "300d5", makes it a
+    // Bronstein delay instead.
+    pub fn parse(text: &str) -> Option<Self> {
+        let seconds = |text: &str| text.parse::<f64>().ok().map(Duration::from_secs_f64);
+        let mut stages = Vec::new();
+
+        for field in text.trim().split(':') {
+            let (moves, rest) = match field.find('/') {
+                Some(index) => (Some(field[..index].parse().ok()?), &field[index + 1..]),
+                None => (None, field),
+            };
+
+            let (time, bonus) = if let Some(index) = rest.find('+') {
+                let increment = seconds(&rest[index + 1..])?;
+                (&rest[..index], Bonus::Increment(increment))
+            } else if let Some(index) = rest.find('d') {
+                (&rest[..index], Bonus::Delay(seconds(&rest[index + 1..])?))
+            } else {
+                (rest, Bonus::None)
+            };
+
+            stages.push(Stage {
+                moves,
+                time: seconds(time)?,
+                bonus,
+            });
+        }
+
+        Some(Self::new(stages))
+    }
+}
+
+// A clock as plain data, with the time the running side has used so far
+// already taken off.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClockState {
+    pub control: TimeControl,
+    pub remaining: [Duration; 2],
+    pub stage: [usize; 2],
+    pub stage_moves: [u32; 2],
+    pub running: Option<Team>,
+}
+
+fn index(team: Team) -> usize {
+    match team {
+        Team::White => 0,
+        Team::Black => 1,
+    }
+}
+
+#[derive(Clone)]
+pub struct Clock {
+    pub control: TimeControl,
+    source: Arc<dyn TimeSource>,
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    stage_moves: [u32; 2],
+    // The side whose time is running and when its turn started.
+    running: Option<(Team, Duration)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl, source: Arc<dyn TimeSource>) -> Self {
+        let time = control
+            .stages
+            .first()
+            .map_or(Duration::from_secs(0), |stage| stage.time);
+
+        Self {
+            control,
+            source,
+            remaining: [time; 2],
+            stage: [0; 2],
+            stage_moves: [0; 2],
+            running: None,
+        }
+    }
+
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            control: self.control.clone(),
+            remaining: [self.remaining(Team::White), self.remaining(Team::Black)],
+            stage: self.stage,
+            stage_moves: self.stage_moves,
+            running: self.running(),
+        }
+    }
+
+    // The clock `state` was taken from, the running side's time going on
+    // from now as `source` reads it.
+    pub fn from_state(state: &ClockState, source: Arc<dyn TimeSource>) -> Self {
+        let now = source.now();
+        Self {
+            control: state.control.clone(),
+            source,
+            remaining: state.remaining,
+            stage: state.stage,
+            stage_moves: state.stage_moves,
+            running: state.running.map(|team| (team, now)),
+        }
+    }
+
+    pub fn start(&mut self, team: Team) {
+        self.stop();
+        self.running = Some((team, self.source.now()));
+    }
+
+    // Stops the running side's time without ending its move.
+    pub fn stop(&mut self) {
+        if let Some((team, started)) = self.running.take() {
+            let elapsed = self.source.now().checked_sub(started).unwrap_or_default();
+            let remaining = &mut self.remaining[index(team)];
+            *remaining = remaining.checked_sub(elapsed).unwrap_or_default();
+        }
+    }
+
+    pub fn running(&self) -> Option<Team> {
+        self.running.map(|(team, _)| team)
+    }
+
+    pub fn remaining(&self, team: Team) -> Duration {
+        let remaining = self.remaining[index(team)];
+        match self.running {
+            Some((running, started)) if running == team => {
+                let elapsed = self.source.now().checked_sub(started).unwrap_or_default();
+                remaining.checked_sub(elapsed).unwrap_or_default()
+            }
+            _ => remaining,
+        }
+    }
+
+    // The side that has run out of time.
+    pub fn flagged(&self) -> Option<Team> {
+        [Team::White, Team::Black]
+            .iter()
+            .find(|team| self.remaining(**team) == Duration::from_secs(0))
+            .copied()
+    }
+
+    // Ends the running side's move: its time is charged, the bonus and any
+    // next stage added, and the other side's time starts. Returns false,
+    // leaving the clock stopped, if the side had already run out.
+    pub fn press(&mut self) -> bool {
+        let (team, started) = match self.running {
+            Some(running) => running,
+            None => return true,
+        };
+
+        let now = self.source.now();
+        let elapsed = now.checked_sub(started).unwrap_or_default();
+        let i = index(team);
+
+        if elapsed >= self.remaining[i] {
+            self.remaining[i] = Duration::from_secs(0);
+            self.running = None;
+            return false;
+        }
+
+        self.remaining[i] -= elapsed;
+
+        let stage = self.control.stages[self.stage[i]];
+        match stage.bonus {
+            Bonus::None => {}
+            Bonus::Increment(increment) => self.remaining[i] += increment,
+            Bonus::Delay(delay) => self.remaining[i] += elapsed.min(delay),
+        }
+
+        self.stage_moves[i] += 1;
+        if stage.moves == Some(self.stage_moves[i]) {
+            self.stage[i] = (self.stage[i] + 1).min(self.control.stages.len() - 1);
+            self.stage_moves[i] = 0;
+            self.remaining[i] += self.control.stages[self.stage[i]].time;
+        }
+
+        self.running = Some((team.opponent(), now));
+        true
+    }
+}
+
+impl Board {
+    // Puts a clock on the game, running for the side to move.
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        clock.start(self.current_player);
+        self.clock = Some(clock);
+    }
+
+    // Ends the mover's turn on the clock. Returns false if its flag fell
+    // during the move.
+    pub(crate) fn press_clock(&mut self) -> bool {
+        self.clock.as_mut().is_none_or(|clock| clock.press())
+    }
+
+    // Gives the turn on the clock back to the side to move after a move is
+    // taken back. The time used and any increment or delay earned for the
+    // move are not given back.
+    pub(crate) fn rewind_clock(&mut self) {
+        if let Some(clock) = self.clock.as_mut() {
+            clock.start(self.current_player);
+        }
+    }
+
+    // Ends the game if a side has run out of time, which loses unless the
+    // other side has nothing left to mate with. Moves check it themselves,
+    // but call it while waiting for a move too, a flag can fall at any time.
+    pub fn check_flag(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        let team = match self.clock.as_ref().and_then(|clock| clock.flagged()) {
+            Some(team) => team,
+            None => return false,
+        };
+
+        let opponent = team.opponent();
+        let winner = if self.has_mating_material(opponent) {
+            Some(opponent)
+        } else {
+            None
+        };
+        self.finish(winner, Termination::Timeout);
+
+        true
+    }
+
+    // Anything more than a bare king, or a king and a single bishop or
+    // knight.
+    pub fn has_mating_material(&self, team: Team) -> bool {
+        let pieces = self.enumerate_pieces(|piece, _| piece.team == team && !piece.necessity);
+
+        match pieces.as_slice() {
+            [] => false,
+            [(x, y)] => {
+                let name = &self.tiles[*x][*y].as_ref().unwrap().name;
+                name != "Bishop" && name != "Knight"
+            }
+            _ => true,
+        }
+    }
+}
@@ -1,20 +1,229 @@
 use crate::*;
+use pieces::custom::{self, PieceDefinition};
 use pieces::default::*;
 
-pub type BoardGenerator = Box<dyn Fn(&mut Vec<Vec<ChessTile>>)>;
+use std::sync::Arc;
+use win::WinCondition;
 
+pub type BoardGenerator = Arc<dyn Fn(&mut Vec<Vec<ChessTile>>) + Send + Sync>;
+
+#[derive(Clone)]
 pub struct BoardConfig {
+    pub width: usize,
+    pub height: usize,
     pub white_stride: i32,
     pub pawn_en_passant: bool,
     pub place_pawns: BoardGenerator,
+    pub pawn_double_step: bool,
+    pub castling: bool,
+    pub chess960: bool,
+    // Captured pieces change sides and can be dropped back, as in Crazyhouse.
+    pub drops: bool,
+    // Captures blow up everything but pawns around them, as in atomic chess.
+    pub atomic: bool,
+    pub win: Arc<dyn WinCondition>,
+    // Whether kings can be checked and mated, off in antichess.
+    pub royal_king: bool,
+    // A side that can capture has to, as in antichess.
+    pub forced_captures: bool,
+    // Pawns may stand on their own first rank and double step from there, as
+    // in Horde.
+    pub back_rank_pawns: bool,
+    pub pieces: Vec<PieceDefinition>,
+    pub promotions: Vec<String>,
 }
 
-impl BoardConfig {
-    pub fn default() -> Self {
+impl Default for BoardConfig {
+    fn default() -> Self {
         Self {
+            width: 8,
+            height: 8,
             white_stride: -1,
             pawn_en_passant: true,
-            place_pawns: Box::new(place_defaults),
+            place_pawns: Arc::new(|tiles| place_defaults(tiles)),
+            pawn_double_step: true,
+            castling: true,
+            chess960: false,
+            drops: false,
+            atomic: false,
+            win: Arc::new(win::Checkmate),
+            royal_king: true,
+            forced_captures: false,
+            back_rank_pawns: false,
+            pieces: Vec::new(),
+            promotions: PROMOTIONS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl BoardConfig {
+    // Pawns on the second rank and `back_rank` behind them, from the a-file
+    // on, on a board as wide as the back rank.
+    pub fn with_back_rank(back_rank: Vec<&'static str>, height: usize) -> Self {
+        Self::with_pieces(Vec::new(), back_rank, height)
+    }
+
+    // Like `with_back_rank`, with custom pieces that can be placed on the back
+    // rank and promoted to.
+    pub fn with_pieces(
+        pieces: Vec<PieceDefinition>,
+        back_rank: Vec<&'static str>,
+        height: usize,
+    ) -> Self {
+        let mut promotions: Vec<String> = PROMOTIONS.iter().map(|name| name.to_string()).collect();
+        promotions.extend(pieces.iter().map(|piece| piece.name.clone()));
+
+        let placed = pieces.clone();
+        Self {
+            width: back_rank.len(),
+            height,
+            place_pawns: Arc::new(move |tiles| place_back_rank(tiles, &back_rank, &placed)),
+            pieces,
+            promotions,
+            ..Self::default()
+        }
+    }
+
+    // 10x8 with the archbishop and chancellor between bishops and knights.
+    pub fn capablanca() -> Self {
+        Self::with_pieces(
+            vec![custom::archbishop(), custom::chancellor()],
+            vec![
+                "Rook",
+                "Knight",
+                "Archbishop",
+                "Bishop",
+                "Queen",
+                "King",
+                "Bishop",
+                "Chancellor",
+                "Knight",
+                "Rook",
+            ],
+            8,
+        )
+    }
+
+    // 6x6 without bishops, pawn double steps or castling.
+    pub fn los_alamos() -> Self {
+        Self {
+            pawn_en_passant: false,
+            pawn_double_step: false,
+            castling: false,
+            ..Self::with_back_rank(vec!["Rook", "Knight", "Queen", "King", "Knight", "Rook"], 6)
+        }
+    }
+
+    // The standard setup where captured pieces go to the capturer's pocket.
+    pub fn crazyhouse() -> Self {
+        Self {
+            drops: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn atomic() -> Self {
+        Self {
+            atomic: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn three_check() -> Self {
+        Self {
+            win: Arc::new(win::ThreeCheck),
+            ..Self::default()
+        }
+    }
+
+    pub fn king_of_the_hill() -> Self {
+        Self {
+            win: Arc::new(win::KingOfTheHill),
+            ..Self::default()
+        }
+    }
+
+    // Both sides start on the first two ranks without pawns, white on the
+    // right, and race their kings to the eighth rank.
+    pub fn racing_kings() -> Self {
+        Self {
+            castling: false,
+            win: Arc::new(win::RacingKings),
+            place_pawns: Arc::new(|tiles| {
+                let ranks = [
+                    ["King", "Rook", "Bishop", "Knight"],
+                    ["Queen", "Rook", "Bishop", "Knight"],
+                ];
+
+                for (y, rank) in ranks.iter().enumerate() {
+                    for (x, name) in rank.iter().enumerate() {
+                        tiles[x][6 + y] = create(&[], name, Team::Black);
+                        tiles[7 - x][6 + y] = create(&[], name, Team::White);
+                    }
+                }
+            }),
+            ..Self::default()
+        }
+    }
+
+    // Losing chess: captures are compulsory, the king is an ordinary piece
+    // pawns may promote to, and losing every piece or being stalemated wins.
+    pub fn antichess() -> Self {
+        let mut promotions: Vec<String> = PROMOTIONS.iter().map(|name| name.to_string()).collect();
+        promotions.push("King".to_owned());
+
+        Self {
+            castling: false,
+            royal_king: false,
+            forced_captures: true,
+            win: Arc::new(win::Antichess),
+            promotions,
+            ..Self::default()
+        }
+    }
+
+    // Black's usual army against 36 white pawns and no white king. White
+    // loses with its last piece.
+    pub fn horde() -> Self {
+        Self {
+            back_rank_pawns: true,
+            place_pawns: Arc::new(|tiles| place_horde(tiles)),
+            ..Self::default()
+        }
+    }
+
+    // A registered custom piece or one of the standard six.
+    pub fn create_piece(&self, name: &str, team: Team) -> Option<Piece> {
+        let mut piece = create(&self.pieces, name, team)?;
+        if !self.royal_king && piece.name == "King" {
+            piece.necessity = false;
+        }
+
+        Some(piece)
+    }
+
+    pub fn piece_letter(&self, name: &str) -> Option<char> {
+        match self.pieces.iter().find(|piece| piece.name == name) {
+            Some(piece) => Some(piece.letter),
+            None => notation::piece_letter(name),
+        }
+    }
+
+    pub fn piece_name(&self, letter: char) -> Option<String> {
+        let letter = letter.to_ascii_uppercase();
+        match self.pieces.iter().find(|piece| piece.letter == letter) {
+            Some(piece) => Some(piece.name.clone()),
+            None => notation::piece_name(letter).map(|name| name.to_owned()),
+        }
+    }
+
+    pub fn chess960(index: usize) -> Self {
+        let back_rank = chess960_back_rank(index);
+
+        Self {
+            place_pawns: Arc::new(move |tiles| place_back_rank(tiles, &back_rank, &[])),
+            chess960: true,
+            ..Self::default()
         }
     }
 }
@@ -25,33 +234,129 @@ macro_rules! create_piece {
     };
 }
 
-pub fn place_defaults(tiles: &mut Vec<Vec<ChessTile>>) {
-    //place pawns
-    for x in 0..8 {
-        tiles[x][6] = create_piece!(pawn, Team::White);
-        tiles[x][1] = create_piece!(pawn, Team::Black);
+fn place_pawn_rows(tiles: &mut [Vec<ChessTile>]) {
+    let height = tiles[0].len();
+    for column in tiles.iter_mut() {
+        column[height - 2] = create_piece!(pawn, Team::White);
+        column[1] = create_piece!(pawn, Team::Black);
     }
+}
+
+// Back rank of Chess960 start position `index` (0 to 959) in the usual
+// numbering, where 518 is the standard setup.
+pub fn chess960_back_rank(index: usize) -> [&'static str; 8] {
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+
+    let mut rank = [""; 8];
+    let mut n = index % 960;
+
+    rank[2 * (n % 4) + 1] = "Bishop";
+    n /= 4;
+    rank[2 * (n % 4)] = "Bishop";
+    n /= 4;
+
+    let empty =
+        |rank: &[&str; 8]| -> Vec<usize> { (0..8).filter(|x| rank[*x].is_empty()).collect() };
+
+    rank[empty(&rank)[n % 6]] = "Queen";
+    n /= 6;
+
+    let (first, second) = KNIGHTS[n];
+    let free = empty(&rank);
+    rank[free[first]] = "Knight";
+    rank[free[second]] = "Knight";
+
+    for (x, name) in empty(&rank)
+        .into_iter()
+        .zip(["Rook", "King", "Rook"].iter())
+    {
+        rank[x] = name;
+    }
+
+    rank
+}
+
+pub fn place_back_rank(
+    tiles: &mut [Vec<ChessTile>],
+    back_rank: &[&str],
+    pieces: &[PieceDefinition],
+) {
+    place_pawn_rows(tiles);
+
+    let back = tiles[0].len() - 1;
+    for (x, name) in back_rank.iter().enumerate() {
+        tiles[x][back] = create(pieces, name, Team::White);
+        tiles[x][0] = create(pieces, name, Team::Black);
+    }
+}
+
+fn create(pieces: &[PieceDefinition], name: &str, team: Team) -> Option<Piece> {
+    match pieces.iter().find(|piece| piece.name == name) {
+        Some(piece) => Some(piece.create(team)),
+        None => by_name(name, team),
+    }
+}
+
+// Four full ranks of white pawns and four more on the fifth, b, c, f and g.
+// Pawns past the second rank have used up their double step.
+fn place_horde(tiles: &mut [Vec<ChessTile>]) {
+    place_defaults(tiles);
+
+    let height = tiles[0].len();
+    for (x, column) in tiles.iter_mut().enumerate() {
+        for (y, tile) in column.iter_mut().enumerate().skip(height - 4) {
+            let mut piece = pawn(Team::White);
+            piece.has_moved = y < height - 2;
+            *tile = Some(piece);
+        }
+
+        column[height - 5] = if [1, 2, 5, 6].contains(&x) {
+            let mut piece = pawn(Team::White);
+            piece.has_moved = true;
+            Some(piece)
+        } else {
+            None
+        };
+    }
+}
+
+pub fn place_defaults(tiles: &mut [Vec<ChessTile>]) {
+    //place pawns
+    place_pawn_rows(tiles);
+
+    let back = tiles[0].len() - 1;
 
-    tiles[4][7] = create_piece!(king, Team::White);
+    tiles[4][back] = create_piece!(king, Team::White);
     tiles[4][0] = create_piece!(king, Team::Black);
 
-    tiles[3][7] = create_piece!(queen, Team::White);
+    tiles[3][back] = create_piece!(queen, Team::White);
     tiles[3][0] = create_piece!(queen, Team::Black);
 
-    tiles[2][7] = create_piece!(bishop, Team::White);
-    tiles[5][7] = create_piece!(bishop, Team::White);
+    tiles[2][back] = create_piece!(bishop, Team::White);
+    tiles[5][back] = create_piece!(bishop, Team::White);
 
     tiles[2][0] = create_piece!(bishop, Team::Black);
     tiles[5][0] = create_piece!(bishop, Team::Black);
 
-    tiles[1][7] = create_piece!(knight, Team::White);
-    tiles[6][7] = create_piece!(knight, Team::White);
+    tiles[1][back] = create_piece!(knight, Team::White);
+    tiles[6][back] = create_piece!(knight, Team::White);
 
     tiles[1][0] = create_piece!(knight, Team::Black);
     tiles[6][0] = create_piece!(knight, Team::Black);
 
-    tiles[0][7] = create_piece!(rook, Team::White);
-    tiles[7][7] = create_piece!(rook, Team::White);
+    tiles[0][back] = create_piece!(rook, Team::White);
+    tiles[7][back] = create_piece!(rook, Team::White);
 
     tiles[0][0] = create_piece!(rook, Team::Black);
     tiles[7][0] = create_piece!(rook, Team::Black);
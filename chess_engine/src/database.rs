@@ -0,0 +1,319 @@
+use crate::*;
+use pgn::{parse_pgn, Game};
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"CEDB";
+
+// Piece letters in the order a material signature lists them.
+const MATERIAL_ORDER: &str = "KQRBNP";
+
+// Material of both sides like "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP", white
+// first, in the order of `MATERIAL_ORDER` and then any other pieces.
+pub fn material_signature(board: &Board) -> String {
+    let side = |team: Team| -> String {
+        let mut letters: Vec<char> = board
+            .enumerate_pieces(|piece, _| piece.team == team)
+            .into_iter()
+            .map(|(x, y)| {
+                let name = &board.tiles[x][y].as_ref().unwrap().name;
+                board.config.piece_letter(name).unwrap_or('?')
+            })
+            .collect();
+
+        letters.sort_by_key(|letter| {
+            (
+                MATERIAL_ORDER.find(*letter).unwrap_or(MATERIAL_ORDER.len()),
+                *letter,
+            )
+        });
+        letters.into_iter().collect()
+    };
+
+    format!("{}v{}", side(Team::White), side(Team::Black))
+}
+
+// What to look for, every field given has to match. Names match any part
+// of the tag, `eco` the start of it, so "B9" finds B90 to B99, and dates
+// compare as PGN writes them ("2020.05.17").
+#[derive(Clone, Default, Debug)]
+pub struct Query {
+    pub player: Option<String>,
+    pub white: Option<String>,
+    pub black: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub result: Option<String>,
+    pub eco: Option<String>,
+    // Polyglot key of a position the game has to reach.
+    pub position: Option<u64>,
+    pub material: Option<String>,
+}
+
+impl Query {
+    fn matches_tags(&self, entry: &Entry) -> bool {
+        let (white, black) = (entry.tag("White"), entry.tag("Black"));
+        let date = entry.tag("Date");
+        let contains = |tag: &str, name: &Option<String>| {
+            name.as_ref().is_none_or(|name| tag.contains(name.as_str()))
+        };
+        let compare = |bound: &Option<String>, test: fn(&str, &str) -> bool| {
+            bound.as_ref().is_none_or(|bound| test(date, bound))
+        };
+
+        contains(white, &self.white)
+            && contains(black, &self.black)
+            && (contains(white, &self.player) || contains(black, &self.player))
+            && compare(&self.date_from, |date, from| date >= from)
+            && compare(&self.date_to, |date, to| date <= to)
+            && self
+                .result
+                .as_ref()
+                .is_none_or(|result| entry.tag("Result") == result)
+            && self
+                .eco
+                .as_ref()
+                .is_none_or(|eco| entry.tag("ECO").starts_with(eco.as_str()))
+    }
+}
+
+struct Entry {
+    bytes: Vec<u8>,
+    tags: Vec<(String, String)>,
+}
+
+impl Entry {
+    fn tag(&self, name: &str) -> &str {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map_or("", |(_, value)| value.as_str())
+    }
+}
+
+// Standard chess games in the binary encoding, indexed by every position
+// and material signature they pass through. Kept in memory and saved to a
+// single file:
+//
+//   "CEDB", then the games as count and length-prefixed encodings, the
+//   positions as count and (key, game) pairs sorted by key, and the material
+//   signatures as count and (length-prefixed signature, game) pairs.
+//
+// Numbers are little endian u32, keys u64.
+#[derive(Default)]
+pub struct Database {
+    games: Vec<Entry>,
+    positions: HashMap<u64, Vec<u32>>,
+    materials: HashMap<String, Vec<u32>>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    // Adds a game, unless its FEN tag doesn't read or one of its moves can't
+    // be played, returning its id.
+    pub fn add_game(&mut self, game: &Game) -> Option<usize> {
+        game.start()?;
+        let (mut board, plies) = game.replay(game.moves.len());
+        if plies.len() != game.moves.len() {
+            return None;
+        }
+
+        let id = self.games.len() as u32;
+        self.games.push(Entry {
+            bytes: binary::encode(&board, &game.tags),
+            tags: game.tags.clone(),
+        });
+
+        let mut positions = HashSet::new();
+        let mut materials = HashSet::new();
+        loop {
+            positions.insert(board.polyglot_key());
+            materials.insert(material_signature(&board));
+            if !board.undo_last() {
+                break;
+            }
+        }
+
+        for key in positions {
+            self.positions.entry(key).or_default().push(id);
+        }
+        for signature in materials {
+            self.materials.entry(signature).or_default().push(id);
+        }
+
+        Some(id as usize)
+    }
+
+    // Adds every game in the PGN text that plays through, returning how many
+    // did.
+    pub fn add_pgn(&mut self, text: &str) -> usize {
+        parse_pgn(text)
+            .iter()
+            .filter_map(|game| self.add_game(game))
+            .count()
+    }
+
+    pub fn import<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        Ok(self.add_pgn(&fs::read_to_string(path)?))
+    }
+
+    pub fn tags(&self, id: usize) -> Option<&[(String, String)]> {
+        self.games.get(id).map(|entry| entry.tags.as_slice())
+    }
+
+    // The final position of a game, with its whole history.
+    pub fn game(&self, id: usize) -> Option<Board> {
+        binary::decode(&self.games.get(id)?.bytes, None).map(|(board, _)| board)
+    }
+
+    // Ids of the matching games, in the order they were added. With a
+    // position or material to look for, only the games the index lists for
+    // them are looked at.
+    pub fn search(&self, query: &Query) -> Vec<usize> {
+        let none = Vec::new();
+        let mut lists = Vec::new();
+        if let Some(key) = query.position {
+            lists.push(self.positions.get(&key).unwrap_or(&none));
+        }
+        if let Some(signature) = &query.material {
+            lists.push(self.materials.get(signature).unwrap_or(&none));
+        }
+        lists.sort_by_key(|ids| ids.len());
+
+        // The id lists are in the order the games were added, so the shortest
+        // one can be checked against the others by binary search.
+        let candidates: Vec<usize> = match lists.split_first() {
+            Some((shortest, others)) => shortest
+                .iter()
+                .filter(|id| others.iter().all(|ids| ids.binary_search(id).is_ok()))
+                .map(|id| *id as usize)
+                .collect(),
+            None => (0..self.games.len()).collect(),
+        };
+
+        candidates
+            .into_iter()
+            .filter(|id| {
+                self.games
+                    .get(*id)
+                    .is_some_and(|entry| query.matches_tags(entry))
+            })
+            .collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+
+        put(&mut bytes, self.games.len());
+        for entry in self.games.iter() {
+            put(&mut bytes, entry.bytes.len());
+            bytes.extend_from_slice(&entry.bytes);
+        }
+
+        let mut positions: Vec<(u64, u32)> = self
+            .positions
+            .iter()
+            .flat_map(|(key, ids)| ids.iter().map(move |id| (*key, *id)))
+            .collect();
+        positions.sort();
+
+        put(&mut bytes, positions.len());
+        for (key, id) in positions {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            put(&mut bytes, id as usize);
+        }
+
+        let mut materials: Vec<(&String, u32)> = self
+            .materials
+            .iter()
+            .flat_map(|(signature, ids)| ids.iter().map(move |id| (signature, *id)))
+            .collect();
+        materials.sort();
+
+        put(&mut bytes, materials.len());
+        for (signature, id) in materials {
+            put(&mut bytes, signature.len());
+            bytes.extend_from_slice(signature.as_bytes());
+            put(&mut bytes, id as usize);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(..4)? != MAGIC {
+            return None;
+        }
+
+        let mut position = 4;
+        let mut database = Self::new();
+
+        for _ in 0..take_number(bytes, &mut position)? {
+            let length = take_number(bytes, &mut position)?;
+            let game = take(bytes, &mut position, length)?.to_vec();
+            database.games.push(Entry {
+                tags: binary::decode_tags(&game)?,
+                bytes: game,
+            });
+        }
+
+        for _ in 0..take_number(bytes, &mut position)? {
+            let mut key = [0; 8];
+            key.copy_from_slice(take(bytes, &mut position, 8)?);
+            let id = take_number(bytes, &mut position)? as u32;
+            database
+                .positions
+                .entry(u64::from_le_bytes(key))
+                .or_default()
+                .push(id);
+        }
+
+        for _ in 0..take_number(bytes, &mut position)? {
+            let length = take_number(bytes, &mut position)?;
+            let signature = String::from_utf8(take(bytes, &mut position, length)?.to_vec()).ok()?;
+            let id = take_number(bytes, &mut position)? as u32;
+            database.materials.entry(signature).or_default().push(id);
+        }
+
+        Some(database)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a game database"))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+fn put(bytes: &mut Vec<u8>, number: usize) {
+    bytes.extend_from_slice(&(number as u32).to_le_bytes());
+}
+
+fn take<'a>(bytes: &'a [u8], position: &mut usize, length: usize) -> Option<&'a [u8]> {
+    let slice = bytes.get(*position..*position + length)?;
+    *position += length;
+    Some(slice)
+}
+
+fn take_number(bytes: &[u8], position: &mut usize) -> Option<usize> {
+    let mut word = [0; 4];
+    word.copy_from_slice(take(bytes, position, 4)?);
+    Some(u32::from_le_bytes(word) as usize)
+}
@@ -0,0 +1,180 @@
+use crate::*;
+
+use std::fmt;
+
+const WHITE_GLYPHS: [(&str, char); 6] = [
+    ("King", '♔'),
+    ("Queen", '♕'),
+    ("Rook", '♖'),
+    ("Bishop", '♗'),
+    ("Knight", '♘'),
+    ("Pawn", '♙'),
+];
+const BLACK_GLYPHS: [(&str, char); 6] = [
+    ("King", '♚'),
+    ("Queen", '♛'),
+    ("Rook", '♜'),
+    ("Bishop", '♝'),
+    ("Knight", '♞'),
+    ("Pawn", '♟'),
+];
+
+// Background colours of the 256 colour ANSI palette.
+const LIGHT_SQUARE: u8 = 180;
+const DARK_SQUARE: u8 = 137;
+const HIGHLIGHT: u8 = 143;
+const TARGET: u8 = 108;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Glyphs {
+    // FEN letters, upper case for white, and "." for empty squares.
+    #[default]
+    Ascii,
+    // Chess symbols for the standard pieces, letters for any others.
+    Unicode,
+}
+
+// How `Board::render` draws the board. Highlighted squares are drawn in
+// brackets, like "[N]", or with their own background when `colours` is on.
+#[derive(Clone, Default, Debug)]
+pub struct RenderOptions {
+    pub glyphs: Glyphs,
+    // Rank numbers on the left and file letters below.
+    pub labels: bool,
+    // Black at the bottom.
+    pub flipped: bool,
+    // The held piece and the squares it can move to.
+    pub selection: bool,
+    pub last_move: bool,
+    // Any other squares to highlight.
+    pub highlights: Vec<Point>,
+    // ANSI escapes for the squares and pieces, for terminals.
+    pub colours: bool,
+}
+
+impl Board {
+    fn glyph(&self, piece: &Piece, glyphs: Glyphs) -> char {
+        let table = if piece.team == Team::White {
+            &WHITE_GLYPHS
+        } else {
+            &BLACK_GLYPHS
+        };
+        let symbol = table
+            .iter()
+            .find(|(name, _)| *name == piece.name)
+            .map(|(_, symbol)| *symbol);
+
+        match (glyphs, symbol) {
+            (Glyphs::Unicode, Some(symbol)) => symbol,
+            _ => {
+                let letter = self.config.piece_letter(&piece.name).unwrap_or('?');
+                if piece.team == Team::White {
+                    letter.to_ascii_uppercase()
+                } else {
+                    letter.to_ascii_lowercase()
+                }
+            }
+        }
+    }
+
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let (width, height) = (self.config.width, self.config.height);
+
+        let mut highlighted = options.highlights.clone();
+        let mut targets = Vec::new();
+        if options.last_move {
+            if let Some(mv) = self.history.front() {
+                highlighted.extend(mv.get_affected_tiles());
+            }
+        }
+        if options.selection {
+            if let Some(held) = self.held_piece {
+                highlighted.push(held);
+                targets = self.get_movable();
+            }
+        }
+
+        let columns: Vec<usize> = if options.flipped {
+            (0..width).rev().collect()
+        } else {
+            (0..width).collect()
+        };
+        let rows: Vec<usize> = if options.flipped {
+            (0..height).rev().collect()
+        } else {
+            (0..height).collect()
+        };
+        // Room for the largest rank number.
+        let label_width = height.to_string().len();
+
+        let mut text = String::new();
+        for y in rows {
+            if options.labels {
+                text += &format!("{:>1$} ", height - y, label_width);
+            }
+
+            for x in columns.iter().copied() {
+                let glyph = self.tiles[x][y]
+                    .as_ref()
+                    .map_or('.', |piece| self.glyph(piece, options.glyphs));
+                let marked = highlighted.contains(&(x, y)) || targets.contains(&(x, y));
+
+                if options.colours {
+                    let background = if highlighted.contains(&(x, y)) {
+                        HIGHLIGHT
+                    } else if targets.contains(&(x, y)) {
+                        TARGET
+                    } else if (x + y) % 2 == 0 {
+                        LIGHT_SQUARE
+                    } else {
+                        DARK_SQUARE
+                    };
+                    let foreground = match &self.tiles[x][y] {
+                        Some(piece) if piece.team == Team::Black => 30,
+                        _ => 97,
+                    };
+                    let glyph = if glyph == '.' { ' ' } else { glyph };
+                    text += &format!("\x1b[48;5;{};{}m {} ", background, foreground, glyph);
+                } else if marked {
+                    text += &format!("[{}]", glyph);
+                } else {
+                    text += &format!(" {} ", glyph);
+                }
+            }
+
+            if options.colours {
+                text += "\x1b[0m";
+            }
+            text.push('\n');
+        }
+
+        if options.labels {
+            text += &" ".repeat(label_width + 1);
+            for x in columns {
+                text += &format!(" {} ", (b'a' + x as u8) as char);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    // Unicode pieces with labels, white at the bottom.
+    pub fn pretty(&self) -> String {
+        self.render(&RenderOptions {
+            glyphs: Glyphs::Unicode,
+            labels: true,
+            ..RenderOptions::default()
+        })
+    }
+}
+
+// The board in ASCII with labels, white at the bottom.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions {
+            labels: true,
+            ..RenderOptions::default()
+        }))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Captured pieces each side holds and may drop back on the board, by name
+// and kept sorted.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pockets {
+    white: Vec<String>,
+    black: Vec<String>,
+}
+
+impl Pockets {
+    pub fn get(&self, team: Team) -> &[String] {
+        match team {
+            Team::White => &self.white,
+            Team::Black => &self.black,
+        }
+    }
+
+    fn get_mut(&mut self, team: Team) -> &mut Vec<String> {
+        match team {
+            Team::White => &mut self.white,
+            Team::Black => &mut self.black,
+        }
+    }
+
+    pub fn add(&mut self, team: Team, name: &str) {
+        let pocket = self.get_mut(team);
+        let index = pocket.iter().position(|other| other.as_str() > name);
+        pocket.insert(index.unwrap_or(pocket.len()), name.to_owned());
+    }
+
+    pub fn remove(&mut self, team: Team, name: &str) -> bool {
+        let pocket = self.get_mut(team);
+        match pocket.iter().position(|other| other == name) {
+            Some(index) => {
+                pocket.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn count(&self, team: Team, name: &str) -> usize {
+        self.get(team).iter().filter(|other| *other == name).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.white.is_empty() && self.black.is_empty()
+    }
+}
+
+impl Board {
+    // Every drop `team` could make, before checking whether it leaves the
+    // king in check. Pawns never go on the first or last rank.
+    pub(crate) fn drop_moves(&self, team: Team) -> Vec<DropMove> {
+        let mut moves = Vec::new();
+        if !self.config.drops {
+            return moves;
+        }
+
+        let mut names = self.pockets.get(team).to_vec();
+        names.dedup();
+
+        let forward = self.config.white_stride * team as i32;
+        let pawn_rank = if forward < 0 {
+            self.config.height - 2
+        } else {
+            1
+        };
+
+        for name in names {
+            let piece = match self.config.create_piece(&name, team) {
+                Some(piece) => piece,
+                None => continue,
+            };
+
+            for x in 0..self.config.width {
+                for y in 0..self.config.height {
+                    let pawn = piece.name == "Pawn";
+                    if !self.is_empty((x, y)) || (pawn && self.is_last_rank(y)) {
+                        continue;
+                    }
+
+                    // A dropped rook or king never castles, a pawn dropped on
+                    // its starting rank may still make the double step.
+                    let mut piece = piece.clone();
+                    piece.has_moved = !pawn || y != pawn_rank;
+                    moves.push(DropMove::new(piece, (x, y)));
+                }
+            }
+        }
+
+        moves
+    }
+
+    pub(crate) fn perform_drop(&mut self, name: &str, to: Point) -> bool {
+        let mv = self
+            .drop_moves(self.current_player)
+            .into_iter()
+            .find(|mv| mv.to == to && mv.piece.name == name);
+
+        let mut mv = match mv {
+            Some(mv) => mv,
+            None => return false,
+        };
+
+        self.deselect();
+        mv.perform(&mut self.tiles);
+        self.update_pockets(&mv, self.current_player, false);
+        self.count_move(&mv);
+
+        self.history.push_front(Box::new(mv));
+        self.swap_team();
+        self.record_check();
+
+        true
+    }
+
+    // Drops the piece called `name` from the side to move's pocket on `to`,
+    // the drop counterpart of `select` and `move_piece`.
+    pub fn drop_piece(&mut self, name: &str, to: Point) -> bool {
+        if !self.perform_drop(name, to) {
+            return false;
+        }
+
+        self.end_turn();
+
+        true
+    }
+
+    // Keeps the pockets in step with a move `team` just performed, or just
+    // took back when `undo` is set. A captured promoted piece is a pawn again
+    // in the capturer's pocket.
+    pub(crate) fn update_pockets(&mut self, mv: &dyn ChessMove, team: Team, undo: bool) {
+        if !self.config.drops {
+            return;
+        }
+
+        if let Some(drop) = mv.as_drop() {
+            if undo {
+                self.pockets.add(team, &drop.piece.name);
+            } else {
+                self.pockets.remove(team, &drop.piece.name);
+            }
+        }
+
+        if let Some(piece) = mv.captured() {
+            let name = if piece.promoted { "Pawn" } else { &piece.name };
+            if undo {
+                self.pockets.remove(team, name);
+            } else {
+                self.pockets.add(team, name);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::*;
+use notation::parse_san;
+use pgn::Game;
+
+use std::fmt;
+use std::sync::OnceLock;
+
+// One opening a line, as code, name and the moves leading to it in SAN,
+// separated by tabs.
+const OPENINGS: &str = include_str!("openings.tsv");
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.eco, self.name)
+    }
+}
+
+// The openings by the Polyglot key of the position their moves reach, so a
+// game that gets there in another order is still found. Where two lines end
+// in the same position the later one in the table wins.
+fn table() -> &'static HashMap<u64, Opening> {
+    static TABLE: OnceLock<HashMap<u64, Opening>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+        for line in OPENINGS.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split('\t');
+            let (eco, name, moves) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(eco), Some(name), Some(moves)) => (eco, name, moves),
+                _ => continue,
+            };
+
+            if let Some(board) = play_line(moves) {
+                table.insert(
+                    board.polyglot_key(),
+                    Opening {
+                        eco: eco.to_owned(),
+                        name: name.to_owned(),
+                    },
+                );
+            }
+        }
+
+        table
+    })
+}
+
+fn play_line(moves: &str) -> Option<Board> {
+    let mut board = Board::new(None);
+    for san in moves.split_whitespace() {
+        let ply = parse_san(&mut board, san)?;
+        if !board.play(&ply) {
+            return None;
+        }
+    }
+
+    Some(board)
+}
+
+// The opening of the deepest known position the game on `board` went
+// through. Only standard chess is classified.
+pub fn classify(board: &Board) -> Option<&'static Opening> {
+    if board.config.width != 8 || board.config.height != 8 {
+        return None;
+    }
+
+    let table = table();
+    let mut board = board.clone();
+    loop {
+        if let Some(opening) = table.get(&board.polyglot_key()) {
+            return Some(opening);
+        }
+
+        if !board.undo_last() {
+            return None;
+        }
+    }
+}
+
+pub fn classify_game(game: &Game) -> Option<&'static Opening> {
+    classify(&game.replay(game.moves.len()).0)
+}
+
+// Fills in the "ECO" and "Opening" tags of a game, replacing any there
+// already. Returns whether its opening was found.
+pub fn tag_game(game: &mut Game) -> bool {
+    let opening = match classify_game(game) {
+        Some(opening) => opening,
+        None => return false,
+    };
+
+    for (name, value) in [("ECO", &opening.eco), ("Opening", &opening.name)].iter() {
+        match game.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => game.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    true
+}
+
+impl Board {
+    pub fn opening(&self) -> Option<&'static Opening> {
+        classify(self)
+    }
+}
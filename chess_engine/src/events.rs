@@ -0,0 +1,98 @@
+use crate::*;
+use result::GameResult;
+
+use std::sync::Arc;
+
+// What happened on the board, sent to listeners as it happens. Moves are
+// those of the game itself, the search and legality checks stay silent.
+pub enum Event<'a> {
+    // `team` made `mv`, now the front of `board.history`.
+    Move {
+        team: Team,
+        mv: &'a dyn ChessMove,
+    },
+    // Sent after the move that made the capture.
+    Capture {
+        team: Team,
+        mv: &'a dyn ChessMove,
+        piece: &'a Piece,
+    },
+    // `team` is in check after the last move.
+    Check(Team),
+    Promotion {
+        team: Team,
+        at: Point,
+        piece: &'a Piece,
+    },
+    // `mv` was taken back and is no longer in the history.
+    Undo {
+        mv: &'a dyn ChessMove,
+    },
+    GameOver(GameResult),
+}
+
+pub type Listener = Arc<dyn Fn(&Board, &Event) + Send + Sync>;
+
+// The board's listeners by id. A copy of a board starts without any, so
+// boards cloned for analysis don't report to the front end.
+#[derive(Default)]
+pub struct Listeners {
+    next: usize,
+    listeners: Vec<(usize, Listener)>,
+}
+
+impl Clone for Listeners {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Board {
+    // Calls `listener` for every event from now on. The id returned takes it
+    // off again with `unsubscribe`.
+    pub fn subscribe<F>(&mut self, listener: F) -> usize
+    where
+        F: Fn(&Board, &Event) + Send + Sync + 'static,
+    {
+        let id = self.listeners.next;
+        self.listeners.next += 1;
+        self.listeners.listeners.push((id, Arc::new(listener)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) -> bool {
+        let before = self.listeners.listeners.len();
+        self.listeners.listeners.retain(|(other, _)| *other != id);
+        self.listeners.listeners.len() != before
+    }
+
+    pub(crate) fn emit(&self, event: Event) {
+        for (_, listener) in self.listeners.listeners.iter() {
+            listener(self, &event);
+        }
+    }
+
+    pub(crate) fn emit_check(&self) {
+        if self.check {
+            self.emit(Event::Check(self.current_player));
+        }
+    }
+
+    // Announces the move at the front of the history and what it captured.
+    pub(crate) fn emit_move(&self) {
+        if self.listeners.listeners.is_empty() {
+            return;
+        }
+
+        let mv = match self.history.front() {
+            Some(mv) => mv.as_ref(),
+            None => return,
+        };
+
+        let team = self.current_enemy;
+        self.emit(Event::Move { team, mv });
+        if let Some(piece) = mv.captured() {
+            self.emit(Event::Capture { team, mv, piece });
+        }
+    }
+}
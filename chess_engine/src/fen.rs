@@ -0,0 +1,401 @@
+use crate::*;
+
+use std::sync::Arc;
+
+pub const STARTING_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn forward(config: &BoardConfig, team: Team) -> i32 {
+    config.white_stride * team as i32
+}
+
+fn back_rank(config: &BoardConfig, team: Team) -> usize {
+    if forward(config, team) < 0 {
+        config.height - 1
+    } else {
+        0
+    }
+}
+
+fn pawn_rank(config: &BoardConfig, team: Team) -> usize {
+    if forward(config, team) < 0 {
+        config.height - 2
+    } else {
+        1
+    }
+}
+
+impl Board {
+    // Reads a position in FEN. The castling field may be standard ("KQkq"),
+    // X-FEN or Shredder-FEN ("HAha"); rights that need Chess960 castling turn
+    // it on in the configuration. Pockets in brackets after the placement, as
+    // in "...RNBQKBNR[Qp]", turn on drops, with "~" marking promoted pieces.
+    // A field like "3+2" after en passant gives the checks each side still
+    // needs and turns on three-check. The move counters may be left out.
+    pub fn from_fen(fen: &str, configuration: Option<BoardConfig>) -> Option<Board> {
+        let mut fields = fen.split_whitespace();
+        let mut placement = fields.next()?;
+        let mut pockets = None;
+        if let Some(index) = placement.find('[') {
+            pockets = Some(placement[index + 1..].strip_suffix(']')?);
+            placement = &placement[..index];
+        }
+
+        let turn = fields.next().unwrap_or("w");
+        let castling = fields.next().unwrap_or("-");
+        let en_passant = fields.next().unwrap_or("-");
+        let mut fields = fields.peekable();
+        let checks = fields.next_if(|field| field.contains('+'));
+        let halfmove_clock = fields.next().map_or(Some(0), |field| field.parse().ok())?;
+        let fullmove_number = fields.next().map_or(Some(1), |field| field.parse().ok())?;
+
+        let mut board = Board::new(configuration);
+        let (width, height) = (board.config.width, board.config.height);
+        board.tiles = vec![vec![None; height]; width];
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != height {
+            return None;
+        }
+
+        for (y, rank) in ranks.iter().enumerate() {
+            let mut x = 0;
+            let mut skip = 0;
+            for c in rank.chars() {
+                // Runs of empty tiles can take two digits on wide boards.
+                if let Some(digit) = c.to_digit(10) {
+                    skip = skip * 10 + digit as usize;
+                    continue;
+                }
+
+                x += skip;
+                skip = 0;
+
+                if c == '~' {
+                    board.tiles[x.checked_sub(1)?][y].as_mut()?.promoted = true;
+                    continue;
+                }
+
+                let team = if c.is_ascii_uppercase() {
+                    Team::White
+                } else {
+                    Team::Black
+                };
+
+                if x >= width {
+                    return None;
+                }
+
+                let name = board.config.piece_name(c)?;
+                let mut piece = board.config.create_piece(&name, team)?;
+                let home = y == pawn_rank(&board.config, team)
+                    || (board.config.back_rank_pawns && y == back_rank(&board.config, team));
+                piece.has_moved = piece.name == "Pawn" && !home;
+                board.tiles[x][y] = Some(piece);
+                x += 1;
+            }
+
+            if x + skip != width {
+                return None;
+            }
+        }
+
+        if let Some(pockets) = pockets {
+            board.config.drops = true;
+            for c in pockets.chars() {
+                let team = if c.is_ascii_uppercase() {
+                    Team::White
+                } else {
+                    Team::Black
+                };
+
+                board.pockets.add(team, &board.config.piece_name(c)?);
+            }
+        }
+
+        match turn {
+            "w" => {}
+            "b" => board.swap_team(),
+            _ => return None,
+        }
+
+        board.set_castling(castling)?;
+
+        if let Some(checks) = checks {
+            board.set_checks(checks)?;
+        }
+
+        if en_passant != "-" {
+            board.set_en_passant(board.parse_square(en_passant)?)?;
+        }
+
+        board.halfmove_clock = halfmove_clock;
+        board.fullmove_number = fullmove_number.max(1);
+
+        board.update_win_status();
+        Some(board)
+    }
+
+    fn set_castling(&mut self, castling: &str) -> Option<()> {
+        let mut rooks = Vec::new();
+
+        for c in castling.chars().filter(|c| *c != '-') {
+            let team = if c.is_ascii_uppercase() {
+                Team::White
+            } else {
+                Team::Black
+            };
+
+            let y = back_rank(&self.config, team);
+            let king = self.enumerate_pieces(|piece, (_, ky)| {
+                piece.name == "King" && piece.team == team && ky == y
+            });
+            let king = king.first()?.0;
+
+            let candidates = self.enumerate_pieces(|piece, (_, ry)| {
+                piece.name == "Rook" && piece.team == team && ry == y
+            });
+
+            let rook = match c.to_ascii_uppercase() {
+                'K' => candidates.into_iter().rfind(|p| p.0 > king)?,
+                'Q' => candidates.into_iter().find(|p| p.0 < king)?,
+                file if file.is_ascii_alphabetic() => {
+                    let x = file as usize - 'A' as usize;
+                    candidates.into_iter().find(|p| p.0 == x)?
+                }
+                _ => return None,
+            };
+
+            let width = self.config.width;
+            if king != width / 2 || (rook.0 != 0 && rook.0 != width - 1) {
+                self.config.chess960 = true;
+            }
+
+            rooks.push(rook);
+        }
+
+        let teams: Vec<Team> = rooks
+            .iter()
+            .map(|(x, y)| self.tiles[*x][*y].as_ref().unwrap().team)
+            .collect();
+
+        for (x, y) in self.enumerate_pieces(|piece, _| piece.name == "Rook" || piece.name == "King")
+        {
+            let piece = self.tiles[x][y].as_mut().unwrap();
+            piece.has_moved = if piece.name == "Rook" {
+                !rooks.contains(&(x, y))
+            } else {
+                !teams.contains(&piece.team)
+            };
+        }
+
+        Some(())
+    }
+
+    fn set_checks(&mut self, checks: &str) -> Option<()> {
+        if self.config.win.check_limit().is_none() {
+            self.config.win = Arc::new(win::ThreeCheck);
+        }
+        let limit = self.config.win.check_limit()?;
+
+        let mut counts = checks.split('+');
+        for team in [Team::White, Team::Black].iter() {
+            let remaining = counts.next()?.parse::<u32>().ok()?;
+            for _ in 0..limit.checked_sub(remaining)? {
+                self.checks_given.push((0, *team));
+            }
+        }
+
+        Some(())
+    }
+
+    // Recreates the double step that allows en passant on `target`, so the
+    // move is in the history like it would be in a played game. The game
+    // still starts after it.
+    fn set_en_passant(&mut self, (x, y): Point) -> Option<()> {
+        let stride = forward(&self.config, self.current_enemy);
+        let (from, to) = (y as i32 - stride, y as i32 + stride);
+        if !self.in_bounds(x as i32, from) || !self.in_bounds(x as i32, to) {
+            return None;
+        }
+
+        let from = (x, from as usize);
+        let to = (x, to as usize);
+
+        let pawn = self.tiles[to.0][to.1].as_ref()?;
+        if pawn.name != "Pawn" || pawn.team != self.current_enemy || !self.is_empty(from) {
+            return None;
+        }
+
+        let mut pawn = self.tiles[to.0][to.1].take();
+        pawn.as_mut().unwrap().has_moved = false;
+        self.tiles[from.0][from.1] = pawn;
+
+        let mut mv = RegularMove::new(from, to);
+        mv.perform(&mut self.tiles);
+        self.history.push_front(Box::new(mv));
+        self.history_start = self.history.len();
+
+        Some(())
+    }
+
+    // Castling rights of `team` as the rooks it may still castle with, the
+    // kingside one first.
+    pub fn castling_rooks(&self, team: Team) -> Vec<Point> {
+        let y = back_rank(&self.config, team);
+        let king = self.enumerate_pieces(|piece, (_, ky)| {
+            piece.name == "King" && piece.team == team && !piece.has_moved && ky == y
+        });
+
+        if king.is_empty() {
+            return Vec::new();
+        }
+
+        let mut rooks = self.enumerate_pieces(|piece, (_, ry)| {
+            piece.name == "Rook" && piece.team == team && !piece.has_moved && ry == y
+        });
+
+        rooks.sort_by_key(|(x, _)| std::cmp::Reverse(*x));
+        rooks
+    }
+
+    fn castling_field(&self, shredder: bool) -> String {
+        let mut field = String::new();
+
+        for team in [Team::White, Team::Black].iter() {
+            let y = back_rank(&self.config, *team);
+            let all = self.enumerate_pieces(|piece, (_, ry)| {
+                piece.name == "Rook" && piece.team == *team && ry == y
+            });
+            let king = self
+                .get_kings()
+                .into_iter()
+                .find(|p| self.is_team(*p, *team));
+
+            for rook in self.castling_rooks(*team) {
+                let king = king.unwrap().0;
+                let outermost = if rook.0 > king {
+                    all.iter().all(|p| p.0 <= rook.0)
+                } else {
+                    all.iter().all(|p| p.0 >= rook.0)
+                };
+
+                let c = if !shredder && outermost {
+                    if rook.0 > king {
+                        'K'
+                    } else {
+                        'Q'
+                    }
+                } else {
+                    (b'A' + rook.0 as u8) as char
+                };
+
+                field.push(if *team == Team::White {
+                    c
+                } else {
+                    c.to_ascii_lowercase()
+                });
+            }
+        }
+
+        if field.is_empty() {
+            field.push('-');
+        }
+
+        field
+    }
+
+    fn fen(&self, shredder: bool) -> String {
+        let mut placement = Vec::new();
+
+        for y in 0..self.config.height {
+            let mut rank = String::new();
+            let mut empty = 0;
+
+            for x in 0..self.config.width {
+                let piece = match self.tiles[x][y].as_ref() {
+                    Some(piece) => piece,
+                    None => {
+                        empty += 1;
+                        continue;
+                    }
+                };
+
+                if empty > 0 {
+                    rank.push_str(&empty.to_string());
+                    empty = 0;
+                }
+
+                let letter = self.config.piece_letter(&piece.name).unwrap_or('?');
+                rank.push(if piece.team == Team::White {
+                    letter
+                } else {
+                    letter.to_ascii_lowercase()
+                });
+
+                if piece.promoted && self.config.drops {
+                    rank.push('~');
+                }
+            }
+
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+
+            placement.push(rank);
+        }
+
+        let mut placement = placement.join("/");
+        if self.config.drops {
+            placement.push('[');
+            for team in [Team::White, Team::Black].iter() {
+                for name in self.pockets.get(*team) {
+                    let letter = self.config.piece_letter(name).unwrap_or('?');
+                    placement.push(if *team == Team::White {
+                        letter
+                    } else {
+                        letter.to_ascii_lowercase()
+                    });
+                }
+            }
+            placement.push(']');
+        }
+
+        let mut en_passant = self
+            .en_passant_square()
+            .map_or("-".to_owned(), |(_, passed)| self.square_name(passed));
+
+        if let Some(limit) = self.config.win.check_limit() {
+            let remaining = |team| limit.saturating_sub(self.checks_given_by(team));
+            en_passant = format!(
+                "{} {}+{}",
+                en_passant,
+                remaining(Team::White),
+                remaining(Team::Black)
+            );
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if self.current_player == Team::White {
+                "w"
+            } else {
+                "b"
+            },
+            self.castling_field(shredder),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    // FEN with X-FEN castling, which is plain FEN for ordinary positions.
+    pub fn to_fen(&self) -> String {
+        self.fen(false)
+    }
+
+    // FEN with the castling rooks always given by file, as in "HAha".
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen(true)
+    }
+}
@@ -1,14 +1,38 @@
+pub mod atomic;
+pub mod binary;
 pub mod chess_move;
+pub mod clock;
 pub mod configuration;
+pub mod database;
+pub mod display;
+pub mod drops;
+pub mod eco;
+pub mod events;
+pub mod fen;
+pub mod notation;
+pub mod pgn;
 pub mod pieces;
+pub mod polyglot;
+pub mod record;
+pub mod result;
+pub mod search;
+pub mod tablebase;
+pub mod variant;
+pub mod win;
 
 use chess_move::*;
+use clock::Clock;
 use configuration::*;
+use drops::Pockets;
+use events::{Event, Listeners};
 use pieces::*;
+use result::{GameResult, Termination};
 
 use std::collections::HashMap;
 use std::collections::VecDeque;
-use std::mem::replace;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[cfg(test)]
 mod tests {
@@ -17,9 +41,7 @@ mod tests {
     #[test]
     fn check() {
         let mut board = Board::new(None);
-        for (x, y) in
-            board.enumerate_pieces(|piece, point| !piece.necessity && piece.name != "Rook")
-        {
+        for (x, y) in board.enumerate_pieces(|piece, _| !piece.necessity && piece.name != "Rook") {
             board.tiles[x][y] = None
         }
 
@@ -61,9 +83,7 @@ mod tests {
     #[test]
     fn castling() {
         let mut board = Board::new(None);
-        for (x, y) in
-            board.enumerate_pieces(|piece, point| !piece.necessity && piece.name != "Rook")
-        {
+        for (x, y) in board.enumerate_pieces(|piece, _| !piece.necessity && piece.name != "Rook") {
             board.tiles[x][y] = None
         }
 
@@ -113,6 +133,16 @@ mod tests {
 
         assert!(board.select((1, 4)));
         assert!(!board.move_piece((1, 2)));
+
+        // Plies have to be legal moves, promotions included.
+        let mut board = Board::from_fen("4k3/P7/8/8/8/8/4P3/4K3 w - - 0 1", None).unwrap();
+        assert!(!board.play(&Ply::promoting((0, 1), (0, 0), "King")));
+        assert!(!board.play(&Ply::promoting((0, 1), (0, 0), "Pawn")));
+        assert!(!board.play(&Ply::new((0, 1), (0, 0))));
+        assert!(!board.play(&Ply::promoting((4, 6), (4, 4), "Queen")));
+        assert!(board.history.is_empty());
+        assert!(board.play(&Ply::promoting((0, 1), (0, 0), "Queen")));
+        assert_eq!(board.get_name((0, 0)), Some("Queen".to_owned()));
     }
 
     #[test]
@@ -155,11 +185,1343 @@ mod tests {
         assert!(board.move_piece((2, 2)));
     }
 
+    #[test]
+    fn search_mate() {
+        let mut board = Board::new(None);
+        for (x, y) in board.enumerate_pieces(|piece, _| !piece.necessity) {
+            board.tiles[x][y] = None
+        }
+
+        board.tiles[0][1] = Some(default::rook(Team::White));
+        board.tiles[7][5] = Some(default::rook(Team::White));
+
+        let mut config = search::SearchConfig {
+            depth: 2,
+            ..Default::default()
+        };
+
+        let result = search::search(&board, &config);
+        assert_eq!(result.best_move, Some(Ply::new((7, 5), (7, 0))));
+        assert_eq!(result.score, search::MATE - 1);
+        assert_eq!(result.lines[0].score, search::Score::Mate(1));
+
+        config.threads = 3;
+        let result = search::search(&board, &config);
+        assert_eq!(result.best_move, Some(Ply::new((7, 5), (7, 0))));
+
+        assert!(board.play(&Ply::new((7, 5), (7, 0))));
+        assert!(board.finished);
+        assert!(board.get_legal_moves().is_empty());
+    }
+
+    #[test]
+    fn search_deterministic() {
+        let board = Board::new(None);
+        let config = search::SearchConfig {
+            depth: 2,
+            ..Default::default()
+        };
+
+        let first = search::search(&board, &config);
+        let second = search::search(&board, &config);
+
+        assert_eq!(first.pv, second.pv);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.score, second.score);
+    }
+
+    #[test]
+    fn search_time() {
+        use std::time::{Duration, Instant};
+
+        let timer =
+            search::time::TimeManager::new(Duration::from_secs(60), Duration::from_secs(1), None);
+        assert!(timer.soft_limit() < timer.hard_limit());
+        assert!(timer.hard_limit() < Duration::from_secs(60));
+
+        let timer = search::time::TimeManager::new(
+            Duration::from_millis(40),
+            Duration::from_secs(0),
+            Some(1),
+        );
+        assert_eq!(timer.hard_limit(), Duration::from_secs(0));
+
+        // Even with no time at all there is a move to play, and right away.
+        let config = search::SearchConfig {
+            depth: search::MAX_DEPTH,
+            time: Some(timer),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        assert!(search::search(&Board::new(None), &config)
+            .best_move
+            .is_some());
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        let board = Board::new(None);
+        let config = search::SearchConfig {
+            depth: search::MAX_DEPTH,
+            time: Some(search::time::TimeManager::fixed(Duration::from_millis(100))),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let result = search::search(&board, &config);
+
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn multi_pv() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        let iterations = Arc::new(AtomicU32::new(0));
+        let counter = iterations.clone();
+
+        let board = Board::new(None);
+        let config = search::SearchConfig {
+            depth: 2,
+            multi_pv: 3,
+            listener: Some(Box::new(move |info| {
+                assert_eq!(info.lines.len(), 3);
+                counter.fetch_add(1, Ordering::Relaxed);
+            })),
+            ..Default::default()
+        };
+
+        let result = search::search(&board, &config);
+        assert_eq!(iterations.load(Ordering::Relaxed), 2);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv, result.pv);
+
+        let firsts: Vec<&Ply> = result.lines.iter().map(|line| &line.pv[0]).collect();
+        assert!(firsts[0] != firsts[1] && firsts[1] != firsts[2] && firsts[0] != firsts[2]);
+    }
+
+    #[test]
+    fn mate_in_n() {
+        let mut board = Board::new(None);
+        for (x, y) in board.enumerate_pieces(|piece, _| !piece.necessity) {
+            board.tiles[x][y] = None
+        }
+
+        board.tiles[0][2] = Some(default::rook(Team::White));
+        board.tiles[1][3] = Some(default::rook(Team::White));
+
+        assert!(!search::mate::solve_mate(&board, 1).is_mate());
+
+        let solution = search::mate::solve_mate(&board, 2);
+        assert!(solution.is_mate());
+        assert!(solution.is_cooked());
+        assert!(solution.solutions.iter().all(|(_, moves)| *moves == 2));
+        assert!(solution
+            .solutions
+            .iter()
+            .any(|(mv, _)| *mv == Ply::new((1, 3), (1, 1))));
+        assert_eq!(solution.pv.len(), 3);
+
+        for mv in solution.pv.iter() {
+            assert!(board.play(mv));
+        }
+
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::White));
+    }
+
+    #[test]
+    fn polyglot_key() {
+        let mut board = Board::new(None);
+        assert_eq!(board.polyglot_key(), 0x463b96181691fc9c);
+
+        let expected = [
+            ("e4", 0x823c9b50fd114196),
+            ("d5", 0x0756b94461c50fb0),
+            ("e5", 0x662fafb965db29d4),
+            ("f5", 0x22a48b5a8e47ff78),
+            ("Ke2", 0x652a607ca3f242c1),
+            ("Kf7", 0x00fdd303c946bdd9),
+        ];
+
+        for (san, key) in expected.iter() {
+            let ply = notation::parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+            assert_eq!(board.polyglot_key(), *key);
+        }
+    }
+
+    #[test]
+    fn opening_book() {
+        let games = pgn::parse_pgn(
+            "[Event \"Test\"]\n[Result \"1-0\"]\n\n1. e4 e5 {main line} 2. Nf3 (2. f4) Nc6 1-0\n\n\
+             [Result \"0-1\"]\n\n1.e4 c5 0-1\n\n1. d4 d5 1/2-1/2",
+        );
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("Event"), Some("Test"));
+        assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3", "Nc6"]);
+
+        let book = polyglot::Book::build(&games, 2, 4);
+        let book = polyglot::Book::from_bytes(&book.to_bytes());
+        assert_eq!(book.entries.len(), 1);
+
+        let board = Board::new(None);
+        assert_eq!(book.moves(&board), vec![(Ply::new((4, 6), (4, 4)), 2)]);
+        assert_eq!(book.pick(&board, 7), Some(Ply::new((4, 6), (4, 4))));
+
+        let castle = Ply::new((4, 7), (6, 7));
+        let encoded = polyglot::encode_move(&board, &castle);
+        assert_eq!(encoded & 0x3f, 7);
+        assert_eq!(polyglot::decode_move(&board, encoded), castle);
+
+        // A move repeated within one game is still only one game.
+        let games = pgn::parse_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 *");
+        assert!(polyglot::Book::build(&games, 2, 6).entries.is_empty());
+    }
+
+    #[test]
+    fn tablebase() {
+        let directory = std::env::temp_dir().join(format!("chess_tb_{}", std::process::id()));
+        tablebase::generate::generate(&directory, "KQvK").unwrap();
+        let tablebase = std::sync::Arc::new(tablebase::Tablebase::open(&directory));
+
+        let mut board = Board::new(None);
+        for (x, y) in board.enumerate_pieces(|piece, _| !piece.necessity) {
+            board.tiles[x][y] = None
+        }
+
+        assert_eq!(tablebase.probe(&board).unwrap().wdl, tablebase::Wdl::Draw);
+
+        board.tiles[4][7] = None;
+        board.tiles[4][0] = None;
+        board.tiles[7][0] = Some(default::king(Team::Black));
+        board.tiles[5][2] = Some(default::king(Team::White));
+        board.tiles[6][5] = Some(default::queen(Team::White));
+
+        let position = tablebase::Position::from_board(&board).unwrap();
+        assert_eq!(
+            tablebase::generate::moves(&position).len(),
+            board.get_legal_moves().len()
+        );
+
+        let probe = tablebase.probe(&board).unwrap();
+        assert_eq!(probe.wdl, tablebase::Wdl::Win);
+        assert_eq!(probe.dtm, Some(1));
+
+        let config = search::SearchConfig {
+            depth: 2,
+            tablebase: Some(tablebase.clone()),
+            ..Default::default()
+        };
+        assert_eq!(search::search(&board, &config).score, search::MATE - 1);
+
+        assert!(board.play(&tablebase.best_move(&board).unwrap()));
+        assert!(board.finished);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn fen() {
+        assert_eq!(Board::new(None).to_fen(), fen::STARTING_POSITION);
+
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        let mut board = Board::from_fen(fen, None).unwrap();
+        assert_eq!(board.to_fen(), fen);
+        assert!(board.get_legal_moves().contains(&Ply::new((4, 3), (3, 2))));
+
+        // The counters go on from the FEN and come back on undo.
+        let fen = "4k3/8/8/8/8/8/4P3/R3K3 w - - 12 40";
+        let mut board = Board::from_fen(fen, None).unwrap();
+        assert_eq!((board.halfmove_clock, board.fullmove_number), (12, 40));
+        assert!(board.play(&Ply::new((0, 7), (0, 4))));
+        assert!(board.play(&Ply::new((4, 0), (3, 0))));
+        assert_eq!(board.to_fen(), "3k4/8/8/8/R7/8/4P3/4K3 w - - 14 41");
+        assert!(board.play(&Ply::new((4, 6), (4, 4))));
+        assert_eq!((board.halfmove_clock, board.fullmove_number), (0, 41));
+        board.undo_last();
+        board.undo_last();
+        assert_eq!((board.halfmove_clock, board.fullmove_number), (13, 40));
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80", None).unwrap();
+        assert!(!board.claim_draw());
+        assert!(board.play(&Ply::new((0, 7), (0, 4))));
+        assert!(board.claim_draw());
+        assert_eq!(
+            board.result.unwrap().termination,
+            result::Termination::FiftyMoves
+        );
+
+        assert!(Board::from_fen("8/8/8/8 w - - 0 1", None).is_none());
+    }
+
+    #[test]
+    fn chess960() {
+        let standard = Board::new(Some(BoardConfig::chess960(518)));
+        assert_eq!(standard.to_fen(), fen::STARTING_POSITION);
+
+        let positions: std::collections::HashSet<_> =
+            (0..960).map(configuration::chess960_back_rank).collect();
+        assert_eq!(positions.len(), 960);
+
+        let board = Board::new(Some(BoardConfig::chess960(0)));
+        let shredder = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1";
+        assert_eq!(board.to_shredder_fen(), shredder);
+        assert_eq!(
+            Board::from_fen(shredder, None).unwrap().to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+
+        // The king's castling target is also an ordinary king move here.
+        let fen = "4k3/8/8/8/8/8/8/5K1R w K - 0 1";
+        let mut board = Board::from_fen(fen, None).unwrap();
+        assert!(board.config.chess960);
+
+        let legal = board.get_legal_moves();
+        assert!(legal.contains(&Ply::new((5, 7), (6, 7))));
+        assert_eq!(
+            notation::parse_san(&mut board, "O-O"),
+            Some(Ply::new((5, 7), (7, 7)))
+        );
+
+        assert!(board.play(&Ply::new((5, 7), (7, 7))));
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+
+        assert!(board.undo_last());
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn board_size() {
+        let capablanca = || {
+            BoardConfig::with_back_rank(
+                vec![
+                    "Rook", "Knight", "Bishop", "Queen", "Knight", "King", "Bishop", "Knight",
+                    "Bishop", "Rook",
+                ],
+                8,
+            )
+        };
+
+        let board = Board::new(Some(capablanca()));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqnkbnbr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNBQNKBNBR w KQkq - 0 1"
+        );
+
+        let mut board = Board::from_fen(
+            "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1",
+            Some(capablanca()),
+        )
+        .unwrap();
+        assert!(!board.config.chess960);
+        assert_eq!(
+            notation::parse_san(&mut board, "Rj2"),
+            Some(Ply::new((9, 7), (9, 6)))
+        );
+
+        let castle = notation::parse_san(&mut board, "O-O").unwrap();
+        assert_eq!(castle, Ply::new((5, 7), (8, 7)));
+        assert!(board.play(&castle));
+        assert_eq!(board.get_name((7, 7)).unwrap(), "Rook");
+
+        let mut board = Board::new(Some(BoardConfig::los_alamos()));
+        assert_eq!(board.square_name((0, 0)), "a6");
+        assert!(board.get_legal_moves().contains(&Ply::new((0, 4), (0, 3))));
+        assert!(!board.get_legal_moves().contains(&Ply::new((0, 4), (0, 2))));
+
+        let mut board =
+            Board::from_fen("6/P4k/6/6/6/K5 w - - 0 1", Some(BoardConfig::los_alamos())).unwrap();
+        assert!(board
+            .get_legal_moves()
+            .contains(&Ply::promoting((0, 1), (0, 0), "Queen")));
+    }
+
+    #[test]
+    fn custom_pieces() {
+        use pieces::custom::*;
+
+        let mut board = Board::new(Some(BoardConfig::capablanca()));
+        assert_eq!(
+            board.to_fen(),
+            "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1"
+        );
+
+        // Knight jumps for the archbishop, nothing else gets out yet.
+        let archbishop: Vec<Ply> = board
+            .get_legal_moves()
+            .into_iter()
+            .filter(|ply| ply.from == (2, 7))
+            .collect();
+        assert_eq!(
+            archbishop,
+            vec![Ply::new((2, 7), (1, 5)), Ply::new((2, 7), (3, 5))]
+        );
+
+        let config = BoardConfig {
+            pieces: vec![
+                grasshopper(),
+                PieceDefinition::new(
+                    "Pusher",
+                    'U',
+                    1,
+                    vec![
+                        Step::leap((0, 1)).only(|(_, y)| y > 0).move_only(),
+                        Step::ride((1, 1)).range(2).capture_only(),
+                    ],
+                ),
+            ],
+            ..BoardConfig::default()
+        };
+
+        let mut board =
+            Board::from_fen("4k3/8/8/3p4/8/3G4/4U3/4K3 w - - 0 1", Some(config)).unwrap();
+        let moves = |board: &mut Board, from: Point| -> Vec<Point> {
+            let mut targets: Vec<Point> = board
+                .get_legal_moves()
+                .into_iter()
+                .filter(|ply| ply.from == from)
+                .map(|ply| ply.to)
+                .collect();
+            targets.sort();
+            targets
+        };
+
+        // The grasshopper can only land right behind the pawn or its own king
+        // and pusher, which leaves d6 and f1 off the board edge.
+        assert_eq!(moves(&mut board, (3, 5)), vec![(3, 2), (5, 7)]);
+        assert_eq!(moves(&mut board, (4, 6)), vec![(4, 5)]);
+
+        board.tiles[6][4] = Some(default::pawn(Team::Black));
+        assert_eq!(moves(&mut board, (4, 6)), vec![(4, 5), (6, 4)]);
+        assert_eq!(board.to_fen(), "4k3/8/8/3p4/6p1/3G4/4U3/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn betza() {
+        use pieces::betza::parse;
+        use pieces::custom::*;
+
+        assert_eq!(parse("N").unwrap(), vec![Step::leap((1, 2))]);
+        assert_eq!(parse("gQ").unwrap(), grasshopper().steps);
+        assert_eq!(parse("BN").unwrap(), archbishop().steps);
+        assert_eq!(parse("W4").unwrap(), vec![Step::ride((1, 0)).range(4)]);
+        assert_eq!(
+            parse("NN").unwrap(),
+            vec![Step::new(all_directions((1, 2)), Kind::Ride(None))]
+        );
+        assert_eq!(parse("ffN").unwrap()[0].offsets, vec![(1, 2), (-1, 2)]);
+        assert_eq!(parse("flF").unwrap()[0].offsets, vec![(-1, 1)]);
+        assert_eq!(
+            parse("fmWfcF").unwrap(),
+            vec![
+                Step::new(vec![(0, 1)], Kind::Leap).move_only(),
+                Step::new(vec![(1, 1), (-1, 1)], Kind::Leap).capture_only(),
+            ]
+        );
+
+        let error = parse("fmWxF").unwrap_err();
+        assert_eq!(error.position, 3);
+        assert_eq!(error.to_string(), "unknown modifier 'x' at position 4");
+        assert_eq!(parse("WfX").unwrap_err().position, 2);
+        assert_eq!(parse("Wmc").unwrap_err().position, 1);
+        assert!(parse("flW").is_err());
+        assert!(parse("").is_err());
+
+        // A pawn written in Betza moves like the real one.
+        let config = BoardConfig {
+            pieces: vec![PieceDefinition::from_betza("Peasant", 'E', 1, "fmWfcFifmW2").unwrap()],
+            ..BoardConfig::default()
+        };
+        let mut board = Board::from_fen("4k3/8/8/8/8/3p4/4E3/4K3 w - - 0 1", Some(config)).unwrap();
+
+        let mut targets: Vec<Point> = board
+            .get_legal_moves()
+            .into_iter()
+            .filter(|ply| ply.from == (4, 6))
+            .map(|ply| ply.to)
+            .collect();
+        targets.sort();
+        assert_eq!(targets, vec![(3, 5), (4, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn variant_file() {
+        use variant::Variant;
+
+        let variant = Variant::parse(include_str!("../../variants/capablanca.toml")).unwrap();
+        assert_eq!(variant.name, "Capablanca");
+        assert_eq!(variant.config.promotions.len(), 6);
+
+        let fen = variant.fen.clone().unwrap();
+        assert_eq!(variant.board().to_fen(), fen);
+        assert_eq!(Board::new(Some(variant.config.clone())).to_fen(), fen);
+
+        let mut board = Variant::parse(include_str!("../../variants/los_alamos.toml"))
+            .unwrap()
+            .board();
+        assert_eq!(board.config.width, 6);
+        assert_eq!(board.get_legal_moves().len(), 10);
+
+        let error = Variant::parse("width = 10\n\n[piece.Wazir]\nletter = \"W\"\nbetza = \"Wx\"")
+            .err()
+            .unwrap();
+        assert_eq!(error.line, 5);
+        assert_eq!(
+            error.to_string(),
+            "line 5: betza: unknown modifier 'x' at position 2"
+        );
+
+        assert_eq!(Variant::parse("castling = yes").err().unwrap().line, 1);
+        assert_eq!(Variant::parse("width = 10").err().unwrap().line, 0);
+    }
+
+    #[test]
+    fn crazyhouse() {
+        let mut board = Board::new(Some(BoardConfig::crazyhouse()));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+        );
+
+        for san in ["e4", "d5", "exd5", "Qxd5"].iter() {
+            let ply = notation::parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+        }
+        assert_eq!(board.pockets.get(Team::White), ["Pawn"]);
+        assert_eq!(board.pockets.get(Team::Black), ["Pawn"]);
+
+        // No pawns on the first or last rank.
+        let legal = board.get_legal_moves();
+        assert!(legal.contains(&Ply::dropping("Pawn", (4, 4))));
+        assert!(!legal.contains(&Ply::dropping("Pawn", (3, 0))));
+
+        let drop = notation::parse_san(&mut board, "P@e4").unwrap();
+        assert_eq!(drop, Ply::dropping("Pawn", (4, 4)));
+        assert!(board.play(&drop));
+        assert!(board.pockets.get(Team::White).is_empty());
+        assert!(board.undo_last());
+        assert!(board.undo_last());
+        assert_eq!(board.pockets.get(Team::White), ["Pawn"]);
+        assert!(board.pockets.get(Team::Black).is_empty());
+
+        // A promoted queen goes back to the pocket as a pawn.
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/Rq~2K3[] w - - 0 1", None).unwrap();
+        assert!(board.check);
+        assert!(board.play(&Ply::new((0, 7), (1, 7))));
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/1R2K3[P] b - - 0 1");
+
+        // Dropping the knight in between is the only way out of the mate.
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/5PPP/r5K1[N] w - - 0 1", None).unwrap();
+        assert!(board.check);
+        assert!(!board.finished);
+        let legal = board.get_legal_moves();
+        assert_eq!(legal.len(), 5);
+        assert!(legal
+            .iter()
+            .all(|ply| ply.drop == Some("Knight".to_owned())));
+    }
+
+    #[test]
+    fn atomic() {
+        let config = || Some(BoardConfig::atomic());
+
+        // The pawn next to the capture survives, the knight and bishop don't.
+        let fen = "7k/8/2nbp3/3q4/8/8/8/K2R4 w - - 0 1";
+        let mut board = Board::from_fen(fen, config()).unwrap();
+        assert!(board.play(&Ply::new((3, 7), (3, 3))));
+        assert_eq!(board.to_fen(), "7k/8/4p3/8/8/8/8/K7 b - - 0 1");
+        assert!(board.undo_last());
+        assert_eq!(board.to_fen(), fen);
+
+        // Kings can't capture, so this is mate.
+        let board = Board::from_fen("7k/8/8/8/8/8/1q6/K7 w - - 0 1", config()).unwrap();
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::Black));
+
+        // Kings next to each other can't be in check.
+        let board = Board::from_fen("8/8/8/3k4/3K4/8/8/3r4 w - - 0 1", config()).unwrap();
+        assert!(!board.check);
+
+        let mut board = Board::from_fen("k7/1p6/8/8/8/8/8/1R5K w - - 0 1", config()).unwrap();
+        assert!(board.play(&Ply::new((1, 7), (1, 1))));
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::White));
+        assert!(board.get_kings().len() == 1);
+    }
+
+    #[test]
+    fn win_conditions() {
+        let board = Board::new(Some(BoardConfig::three_check()));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1"
+        );
+
+        let fen = "4k3/8/8/8/8/8/8/4K2R w - - 1+3 0 1";
+        let mut board = Board::from_fen(fen, None).unwrap();
+        assert_eq!(board.config.win.name(), "three-check");
+        assert!(board.play(&Ply::new((7, 7), (7, 0))));
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::White));
+        assert!(board.undo_last());
+        assert_eq!(board.to_fen(), fen);
+
+        let config = Some(BoardConfig::king_of_the_hill());
+        let mut board = Board::from_fen("4k3/8/8/8/8/3K4/8/8 w - - 0 1", config).unwrap();
+        assert!(board.play(&Ply::new((3, 5), (3, 4))));
+        assert_eq!(board.winner, Some(Team::White));
+
+        // Checks are not allowed, so the knight can't go to c3.
+        let mut board = Board::new(Some(BoardConfig::racing_kings()));
+        assert_eq!(board.to_fen(), "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1");
+        assert!(!board.get_legal_moves().contains(&Ply::new((4, 6), (2, 5))));
+
+        let config = || Some(BoardConfig::racing_kings());
+        let mut board = Board::from_fen("8/7K/k7/8/8/8/8/8 w - - 0 1", config()).unwrap();
+        assert!(board.play(&Ply::new((7, 1), (7, 0))));
+        assert_eq!(board.winner, Some(Team::White));
+
+        // Black reaching the last rank right after white is a draw.
+        let mut board = Board::from_fen("8/k6K/8/8/8/8/8/8 w - - 0 1", config()).unwrap();
+        assert!(board.play(&Ply::new((7, 1), (7, 0))));
+        assert!(!board.finished);
+        assert!(board.play(&Ply::new((0, 1), (1, 0))));
+        assert!(board.finished);
+        assert_eq!(board.winner, None);
+
+        let variant = variant::Variant::parse("win = \"king-of-the-hill\"").unwrap();
+        assert_eq!(variant.config.win.name(), "king-of-the-hill");
+        assert!(variant::Variant::parse("win = \"bare-king\"").is_err());
+    }
+
+    #[test]
+    fn antichess() {
+        let mut board = Board::new(Some(BoardConfig::antichess()));
+        assert!(board.get_kings().is_empty());
+        assert_eq!(board.get_legal_moves().len(), 20);
+
+        assert!(board.play(&Ply::new((4, 6), (4, 4))));
+        assert!(board.play(&Ply::new((1, 1), (1, 3))));
+        assert_eq!(board.get_legal_moves(), vec![Ply::new((5, 7), (1, 3))]);
+        assert!(board.select((0, 6)));
+        assert!(board.get_movable().is_empty());
+
+        let config = || Some(BoardConfig::antichess());
+        let mut board = Board::from_fen("8/P7/8/8/8/8/8/7k w - - 0 1", config()).unwrap();
+        assert!(board
+            .get_legal_moves()
+            .contains(&Ply::promoting((0, 1), (0, 0), "King")));
+
+        // Losing the last piece wins.
+        let mut board = Board::from_fen("8/8/8/8/1p6/2N5/8/8 b - - 0 1", config()).unwrap();
+        assert!(board.play(&Ply::new((1, 4), (2, 5))));
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::White));
+
+        // So does being stalemated.
+        let board = Board::from_fen("8/8/8/8/8/p7/P7/8 w - - 0 1", config()).unwrap();
+        assert_eq!(board.winner, Some(Team::White));
+    }
+
+    #[test]
+    fn horde() {
+        let fen = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+        let mut board = Board::new(Some(BoardConfig::horde()));
+        assert_eq!(board.to_fen(), fen);
+        assert!(!board.can_promote());
+
+        let variant = variant::Variant::parse(include_str!("../../variants/horde.toml")).unwrap();
+        assert_eq!(variant.board().to_fen(), fen);
+
+        // Only the b, c, f and g pawns on the fourth rank can still move,
+        // and those on the fifth rank, one step each.
+        let legal = board.get_legal_moves();
+        assert_eq!(legal.len(), 8);
+        assert!(!legal.contains(&Ply::new((1, 4), (1, 2))));
+
+        // A first-rank pawn double steps once the way is free.
+        let config = || Some(BoardConfig::horde());
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1", config()).unwrap();
+        assert!(board.get_legal_moves().contains(&Ply::new((0, 7), (0, 5))));
+
+        // That double step can't be taken en passant.
+        let mut board = Board::from_fen("4k3/8/8/8/8/1p6/8/P3K3 w - - 0 1", config()).unwrap();
+        assert!(board.play(&Ply::new((0, 7), (0, 5))));
+        assert!(!board.get_legal_moves().contains(&Ply::new((1, 5), (0, 6))));
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/Pp6/8/4K3 b - - 0 1");
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/1q6/P7 b - - 0 1", config()).unwrap();
+        assert!(!board.check);
+        assert!(board.play(&Ply::new((1, 6), (0, 7))));
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::Black));
+    }
+
+    #[test]
+    fn clock() {
+        use clock::*;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let seconds = Duration::from_secs;
+        let time = ManualTime::new();
+        let source = || -> Arc<dyn TimeSource> { Arc::new(time.clone()) };
+
+        // Fischer: the increment comes after every move.
+        let mut clock = Clock::new(TimeControl::fischer(seconds(60), seconds(1)), source());
+        clock.start(Team::White);
+        time.advance(seconds(10));
+        assert!(clock.press());
+        assert_eq!(clock.remaining(Team::White), seconds(51));
+        time.advance(seconds(5));
+        assert_eq!(clock.remaining(Team::Black), seconds(55));
+
+        // Bronstein: the time used comes back, up to the delay.
+        let mut clock = Clock::new(TimeControl::bronstein(seconds(60), seconds(3)), source());
+        clock.start(Team::White);
+        time.advance(seconds(2));
+        clock.press();
+        assert_eq!(clock.remaining(Team::White), seconds(60));
+        clock.press();
+        time.advance(seconds(5));
+        clock.press();
+        assert_eq!(clock.remaining(Team::White), seconds(58));
+
+        // 2/60:30, the second stage's time added after the second move.
+        let control = TimeControl::parse("2/60:30").unwrap();
+        assert_eq!(control.stages.len(), 2);
+        assert_eq!(
+            TimeControl::parse("300+2"),
+            Some(TimeControl::fischer(seconds(300), seconds(2)))
+        );
+        let mut clock = Clock::new(control, source());
+        clock.start(Team::White);
+        for _ in 0..4 {
+            time.advance(seconds(10));
+            clock.press();
+        }
+        assert_eq!(clock.remaining(Team::White), seconds(70));
+
+        // Running out of time loses when moving and while thinking.
+        let mut board = Board::new(None);
+        board.set_clock(Clock::new(TimeControl::sudden_death(seconds(10)), source()));
+        time.advance(seconds(1));
+        assert!(board.play(&Ply::new((4, 6), (4, 4))));
+        assert_eq!(board.clock.as_ref().unwrap().running(), Some(Team::Black));
+        time.advance(seconds(20));
+        assert!(board.play(&Ply::new((4, 1), (4, 3))));
+        assert!(board.finished);
+        assert_eq!(board.winner, Some(Team::White));
+
+        // Unless the other side can't mate with what it has left.
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4p3/4KN2 b - - 0 1", None).unwrap();
+        board.set_clock(Clock::new(TimeControl::sudden_death(seconds(10)), source()));
+        assert!(!board.check_flag());
+        time.advance(seconds(10));
+        assert!(board.check_flag());
+        assert!(board.finished);
+        assert_eq!(board.winner, None);
+
+        // Taking a move back hands the clock back to the mover, without
+        // giving back the time or the increment.
+        let mut board = Board::new(None);
+        let control = TimeControl::fischer(seconds(300), seconds(2));
+        board.set_clock(Clock::new(control, source()));
+        time.advance(seconds(5));
+        assert!(board.play(&Ply::new((4, 6), (4, 4))));
+        assert_eq!(
+            board.clock.as_ref().unwrap().remaining(Team::White),
+            seconds(297)
+        );
+        time.advance(seconds(1));
+        assert!(board.undo_last());
+        let clock = board.clock.as_ref().unwrap();
+        assert_eq!(clock.running(), Some(Team::White));
+        assert_eq!(clock.remaining(Team::Black), seconds(299));
+        time.advance(seconds(7));
+        assert_eq!(
+            board.clock.as_ref().unwrap().remaining(Team::White),
+            seconds(290)
+        );
+    }
+
+    #[test]
+    fn game_result() {
+        use result::*;
+
+        let play = |board: &mut Board, plies: &[(Point, Point)]| {
+            for (from, to) in plies {
+                assert!(board.play(&Ply::new(*from, *to)));
+            }
+        };
+
+        let mut board = Board::new(None);
+        play(
+            &mut board,
+            &[
+                ((5, 6), (5, 5)),
+                ((4, 1), (4, 3)),
+                ((6, 6), (6, 4)),
+                ((3, 0), (7, 4)),
+            ],
+        );
+        let result = board.result.unwrap();
+        assert_eq!(result.termination, Termination::Checkmate);
+        assert_eq!(result.score(), "0-1");
+        board.undo_last();
+        assert_eq!(board.result, None);
+
+        let mut board = Board::from_fen("k7/8/2Q5/8/8/8/8/7K w - - 0 1", None).unwrap();
+        play(&mut board, &[((2, 2), (2, 1))]);
+        assert_eq!(board.result.unwrap().termination, Termination::Stalemate);
+        assert_eq!(board.winner, None);
+
+        let mut board = Board::from_fen("k7/8/8/8/8/8/1r6/K6B w - - 0 1", None).unwrap();
+        play(&mut board, &[((0, 7), (1, 6))]);
+        assert_eq!(
+            board.result.unwrap().termination,
+            Termination::InsufficientMaterial
+        );
+
+        // A draw offer lapses when the other side moves instead of answering.
+        let mut board = Board::new(None);
+        assert!(board.offer_draw(Team::White));
+        assert!(!board.accept_draw(Team::White));
+        play(&mut board, &[((4, 6), (4, 4)), ((4, 1), (4, 3))]);
+        assert_eq!(board.draw_offer, None);
+        assert!(board.offer_draw(Team::Black));
+        assert!(board.decline_draw(Team::White));
+        assert!(board.offer_draw(Team::Black));
+        assert!(board.accept_draw(Team::White));
+        assert_eq!(board.result.unwrap().termination, Termination::Agreement);
+        assert!(!board.resign(Team::White));
+
+        let mut board = Board::new(None);
+        assert!(board.resign(Team::White));
+        assert_eq!(board.winner, Some(Team::Black));
+        assert_eq!(board.result.unwrap().termination, Termination::Resignation);
+
+        // The starting position comes back after every four knight moves.
+        let mut board = Board::new(None);
+        let knights = [
+            ((6, 7), (5, 5)),
+            ((6, 0), (5, 2)),
+            ((5, 5), (6, 7)),
+            ((5, 2), (6, 0)),
+        ];
+        play(&mut board, &knights);
+        assert!(!board.claim_draw());
+        play(&mut board, &knights);
+        assert!(board.claim_draw());
+        assert_eq!(board.result.unwrap().termination, Termination::Repetition);
+    }
+
+    #[test]
+    fn events() {
+        use events::Event;
+        use std::sync::{Arc, Mutex};
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut board = Board::new(None);
+        let sink = log.clone();
+        let id = board.subscribe(move |_, event| {
+            let entry = match event {
+                Event::Move { team, mv } => format!("move {:?} {:?}", team, mv.get_target_tile()),
+                Event::Capture { piece, .. } => format!("capture {}", piece.name),
+                Event::Check(team) => format!("check {:?}", team),
+                Event::Promotion { piece, .. } => format!("promotion {}", piece.name),
+                Event::Undo { mv } => format!("undo {:?}", mv.get_target_tile()),
+                Event::GameOver(result) => format!("over {}", result.score()),
+            };
+            sink.lock().unwrap().push(entry);
+        });
+
+        for (from, to) in [
+            ((5, 6), (5, 5)),
+            ((4, 1), (4, 3)),
+            ((6, 6), (6, 4)),
+            ((3, 0), (7, 4)),
+        ]
+        .iter()
+        {
+            assert!(board.play(&Ply::new(*from, *to)));
+        }
+        board.undo_last();
+
+        let expected = [
+            "move White (5, 5)",
+            "move Black (4, 3)",
+            "move White (6, 4)",
+            "move Black (7, 4)",
+            "check White",
+            "over 0-1",
+            "undo (7, 4)",
+        ];
+        assert_eq!(*log.lock().unwrap(), expected);
+
+        // Copies don't report, and unsubscribing stops the events.
+        let mut copy = board.clone();
+        assert!(copy.play(&Ply::new((3, 0), (7, 4))));
+        assert!(board.unsubscribe(id));
+        assert!(board.play(&Ply::new((3, 0), (7, 4))));
+        assert_eq!(log.lock().unwrap().len(), expected.len());
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", None).unwrap();
+        board.subscribe(move |_, event| {
+            if let Event::Promotion { piece, .. } = event {
+                sink.lock().unwrap().push(piece.name.clone());
+            }
+        });
+        assert!(board.play(&Ply::promoting((1, 1), (1, 0), "Queen")));
+        assert_eq!(*log.lock().unwrap(), ["Queen"]);
+    }
+
+    #[test]
+    fn game_state() {
+        use clock::*;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let time = ManualTime::new();
+        let source = || -> Arc<dyn TimeSource> { Arc::new(time.clone()) };
+        let mut board = Board::new(None);
+        let control = TimeControl::fischer(Duration::from_secs(300), Duration::from_secs(2));
+        board.set_clock(Clock::new(control, source()));
+
+        // En passant, captures and castling on both sides.
+        let moves = "e4 Nf6 e5 d5 exd6 exd6 Nf3 Be7 Bc4 O-O O-O";
+        for san in moves.split(' ') {
+            time.advance(Duration::from_secs(3));
+            let ply = notation::parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+        }
+        assert!(board.offer_draw(Team::White));
+
+        let state = board.to_state();
+        assert_eq!(state.history.len(), 11);
+
+        #[cfg(feature = "serde")]
+        let state: record::GameState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+
+        let mut restored = Board::from_state(&state, None).unwrap();
+        restored.clock = Some(Clock::from_state(state.clock.as_ref().unwrap(), source()));
+        assert_eq!(restored.to_state(), state);
+        assert_eq!(restored.to_fen(), board.to_fen());
+
+        while board.undo_last() {
+            assert!(restored.undo_last());
+            assert_eq!(restored.to_fen(), board.to_fen());
+        }
+        assert!(!restored.undo_last());
+        assert_eq!(restored.to_fen(), Board::new(None).to_fen());
+
+        // The rules a FEN turned on come back with the state.
+        let games = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+                "e4 d5 exd5 Qxd5 Nc3 Qa5 P@d5",
+            ),
+            (
+                "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 3+3 0 1",
+                "O-O O-O g3 g6",
+            ),
+        ];
+        for (fen, moves) in games.iter() {
+            let mut board = Board::from_fen(fen, None).unwrap();
+            for san in moves.split(' ') {
+                let ply = notation::parse_san(&mut board, san).unwrap();
+                assert!(board.play(&ply));
+            }
+
+            let state = board.to_state();
+            #[cfg(feature = "serde")]
+            let state: record::GameState =
+                serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+
+            let mut restored = Board::from_state(&state, None).unwrap();
+            assert_eq!(restored.config.chess960, board.config.chess960);
+            assert_eq!(restored.config.drops, board.config.drops);
+            assert_eq!(restored.config.win.name(), board.config.win.name());
+            assert_eq!(restored.to_fen(), board.to_fen());
+            assert_eq!(restored.get_legal_moves(), board.get_legal_moves());
+
+            while board.undo_last() {
+                assert!(restored.undo_last());
+                assert_eq!(restored.to_fen(), board.to_fen());
+            }
+        }
+
+        // A win condition of the caller's own comes back from the
+        // configuration it is restored with.
+        struct FirstCapture;
+        impl win::WinCondition for FirstCapture {
+            fn name(&self) -> &'static str {
+                "first-capture"
+            }
+
+            fn decide(&self, board: &mut Board) -> Option<win::Decision> {
+                let captured = board.history.front()?.captured().is_some();
+                if captured {
+                    Some(win::Decision::Win(board.current_enemy))
+                } else {
+                    None
+                }
+            }
+        }
+
+        let config = || BoardConfig {
+            win: Arc::new(FirstCapture),
+            ..BoardConfig::default()
+        };
+        let mut board = Board::new(Some(config()));
+        for san in "e4 d5".split(' ') {
+            let ply = notation::parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+        }
+
+        let state = board.to_state();
+        assert_eq!(state.win, "first-capture");
+        assert!(Board::from_state(&state, None).is_none());
+
+        let mut restored = Board::from_state(&state, Some(config())).unwrap();
+        assert_eq!(restored.to_state(), state);
+        let ply = notation::parse_san(&mut restored, "exd5").unwrap();
+        assert!(restored.play(&ply));
+        assert_eq!(restored.winner, Some(Team::White));
+    }
+
+    #[test]
+    fn binary() {
+        let mut board = Board::new(None);
+        let moves = "e4 e5 Nf3 Nc6 Bc4 Nf6 O-O Bc5 d4 exd4 e5 d5 exf6 dxc4 Re1+ Be6 Ng5 Qxf6";
+        for san in moves.split(' ') {
+            let ply = notation::parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+        }
+        board.resign(Team::White);
+
+        let tags = vec![("White".to_owned(), "Gestsson".to_owned())];
+        let bytes = binary::encode(&board, &tags);
+        // Tags, result, start and count take 20 bytes, the 18 moves 12.
+        assert_eq!(bytes.len(), 20 + 12);
+
+        let (decoded, decoded_tags) = binary::decode(&bytes, None).unwrap();
+        assert_eq!(decoded_tags, tags);
+        assert_eq!(decoded.to_state(), board.to_state());
+
+        // Other starting positions are kept, and so is the piece chosen for a
+        // promotion.
+        let mut board = Board::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1", None).unwrap();
+        assert!(board.play(&Ply::promoting((0, 1), (0, 0), "Knight")));
+        let (decoded, _) = binary::decode(&binary::encode(&board, &[]), None).unwrap();
+        assert_eq!(decoded.to_fen(), board.to_fen());
+        assert_eq!(decoded.history.len(), 1);
+    }
+
+    #[test]
+    fn database() {
+        use database::*;
+
+        let pgn = r#"
+[White "Carlsen"]
+[Black "Nakamura"]
+[Date "2019.06.04"]
+[Result "1-0"]
+[ECO "C65"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. d3 Bc5 5. Bxc6 dxc6 1-0
+
+[White "Nakamura"]
+[Black "Caruana"]
+[Date "2021.01.20"]
+[Result "0-1"]
+[ECO "A00"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#;
+        let mut database = Database::new();
+        assert_eq!(database.add_pgn(pgn), 2);
+
+        let query = |query: Query| database.search(&query);
+        let name = |name: &str| Some(name.to_owned());
+        assert_eq!(query(Query::default()), [0, 1]);
+        assert_eq!(
+            query(Query {
+                player: name("Nakamura"),
+                ..Query::default()
+            }),
+            [0, 1]
+        );
+        assert_eq!(
+            query(Query {
+                white: name("Nakamura"),
+                ..Query::default()
+            }),
+            [1]
+        );
+        assert_eq!(
+            query(Query {
+                result: name("1-0"),
+                ..Query::default()
+            }),
+            [0]
+        );
+        assert_eq!(
+            query(Query {
+                eco: name("C6"),
+                ..Query::default()
+            }),
+            [0]
+        );
+        assert_eq!(
+            query(Query {
+                date_from: name("2020.01.01"),
+                ..Query::default()
+            }),
+            [1]
+        );
+
+        // Every game passes through the start, only one after 1. e4 e5.
+        let mut board = Board::new(None);
+        let position = |board: &Board| Query {
+            position: Some(board.polyglot_key()),
+            ..Query::default()
+        };
+        assert_eq!(query(position(&board)), [0, 1]);
+        board.play(&Ply::new((4, 6), (4, 4)));
+        board.play(&Ply::new((4, 1), (4, 3)));
+        assert_eq!(query(position(&board)), [0]);
+
+        // After the exchange on c6 white is missing a bishop, black a knight.
+        let material = name("KQRRBNNPPPPPPPPvKQRRBBNPPPPPPPP");
+        assert_eq!(
+            query(Query {
+                material,
+                ..Query::default()
+            }),
+            [0]
+        );
+        assert_eq!(
+            material_signature(&database.game(1).unwrap()),
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP"
+        );
+
+        let loaded = Database::from_bytes(&database.to_bytes()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.tags(1), database.tags(1));
+        assert_eq!(loaded.search(&position(&board)), [0]);
+        assert!(loaded.game(1).unwrap().finished);
+
+        // A game set up from a FEN starts there, one whose FEN doesn't read
+        // is left out.
+        let pgn = r#"
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 *
+
+[SetUp "1"]
+[FEN "4k3/8/8 w - - 0 1"]
+
+*
+"#;
+        let mut database = Database::new();
+        assert!(database.is_empty());
+        assert_eq!(database.add_pgn(pgn), 1);
+        assert!(!database.is_empty());
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", None).unwrap();
+        assert_eq!(database.search(&position(&board)), [0]);
+        assert_eq!(
+            database.game(0).unwrap().to_fen(),
+            "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2"
+        );
+    }
+
+    #[test]
+    fn eco() {
+        use pgn::parse_pgn;
+
+        let play = |moves: &str| {
+            let mut board = Board::new(None);
+            for san in moves.split_whitespace() {
+                let ply = notation::parse_san(&mut board, san).unwrap();
+                assert!(board.play(&ply));
+            }
+            board
+        };
+
+        // Every line in the table has to play through to its own opening, so
+        // no two lines may reach the same position, and every code from A00
+        // to E99 needs at least one.
+        let mut codes = std::collections::HashSet::new();
+        for line in include_str!("eco/openings.tsv").lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            assert_eq!(fields.len(), 3, "{}", line);
+            let opening = play(fields[2]).opening().expect(line);
+            assert_eq!(
+                (opening.eco.as_str(), opening.name.as_str()),
+                (fields[0], fields[1]),
+                "{}",
+                line
+            );
+            codes.insert(fields[0]);
+        }
+        assert_eq!(codes.len(), 500);
+
+        let opening = |moves: &str| play(moves).opening().map(|opening| opening.to_string());
+        assert_eq!(
+            opening("e4 e5 Nf3 Nc6 Bb5 Nf6"),
+            Some("C65 Ruy Lopez, Berlin Defence".to_owned())
+        );
+        // Moves past the table keep the deepest opening, other move orders
+        // find the same one.
+        assert_eq!(
+            opening("e4 e5 Nf3 Nc6 Bb5 Nf6 d3 Bc5"),
+            Some("C65 Ruy Lopez, Berlin Defence".to_owned())
+        );
+        assert_eq!(
+            opening("c4 e6 Nc3 d5 d4 Nf6"),
+            Some("D35 Queen's Gambit Declined, 3...Nf6".to_owned())
+        );
+        assert_eq!(opening(""), None);
+
+        let mut game =
+            parse_pgn("[ECO \"A00\"]\n\n1. e4 c5 2. Nf3 d6 3. d4 cxd4 4. Nxd4 Nf6 5. Nc3 a6 *")
+                .remove(0);
+        assert!(eco::tag_game(&mut game));
+        assert_eq!(game.tag("ECO"), Some("B90"));
+        assert_eq!(
+            game.tag("Opening"),
+            Some("Sicilian Defence, Najdorf Variation")
+        );
+    }
+
+    #[test]
+    fn san_and_uci() {
+        use notation::*;
+
+        // En passant, castling on both sides and a capture.
+        let moves = "e4 d5 e5 f5 exf6 Nc6 Nf3 Bg4 Bb5 Qd7 O-O O-O-O Bxc6";
+        let mut board = Board::new(None);
+        for san in moves.split(' ') {
+            let ply = parse_san(&mut board, san).unwrap();
+            assert!(board.play(&ply));
+        }
+        assert_eq!(
+            board.history_san().join(" "),
+            moves.replace("exf6", "exf6 e.p.")
+        );
+        assert_eq!(
+            board.history_uci().join(" "),
+            "e2e4 d7d5 e4e5 f7f5 e5f6 b8c6 g1f3 c8g4 f1b5 d8d7 e1g1 e8c8 b5c6"
+        );
+
+        // The double step a FEN sets up for en passant isn't part of the game.
+        let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        let mut board = Board::from_fen(fen, None).unwrap();
+        assert!(board.history_san().is_empty());
+        let ply = parse_san(&mut board, "exd6").unwrap();
+        assert!(board.play(&ply));
+        assert_eq!(board.history_san(), ["exd6 e.p."]);
+        assert!(board.undo_last());
+        assert!(!board.undo_last());
+        assert_eq!(board.to_fen(), fen);
+
+        // Only as much of the origin as tells the pieces apart.
+        let board = Board::from_fen("7k/8/8/R7/8/1Q6/8/RN1Q1NK1 w - - 0 1", None).unwrap();
+        let san = |from: &str, to: &str| {
+            let ply = Ply::new(parse_square(from).unwrap(), parse_square(to).unwrap());
+            format_san(&board, &ply).unwrap()
+        };
+        assert_eq!(san("a1", "a3"), "R1a3");
+        assert_eq!(san("b1", "d2"), "Nbd2");
+        assert_eq!(san("b3", "d3"), "Qbd3");
+        assert_eq!(san("d1", "c2"), "Qdc2");
+        assert_eq!(format_san(&board, &Ply::new((0, 7), (0, 0))), None);
+
+        let mut board = Board::from_fen("7k/8/8/8/8/Q7/8/Q1Q3K1 w - - 0 1", None).unwrap();
+        let ply = parse_uci(&mut board, "a1b2").unwrap();
+        assert_eq!(format_san(&board, &ply).unwrap(), "Qa1b2+");
+
+        // Promotion with mate.
+        let mut board = Board::from_fen("k7/4P3/1K6/8/8/8/8/8 w - - 0 1", None).unwrap();
+        let ply = parse_uci(&mut board, "e7e8q").unwrap();
+        assert_eq!(ply, Ply::promoting((4, 1), (4, 0), "Queen"));
+        assert_eq!(format_san(&board, &ply).unwrap(), "e8=Q#");
+        assert_eq!(format_uci(&board, &ply), "e7e8q");
+        assert_eq!(parse_uci(&mut board, "e7e8k"), None);
+
+        // A drop giving check.
+        let config = Some(BoardConfig::crazyhouse());
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1", config).unwrap();
+        let ply = parse_uci(&mut board, "N@d6").unwrap();
+        assert_eq!(format_san(&board, &ply).unwrap(), "N@d6+");
+        assert!(board.play(&ply));
+        assert_eq!(board.history_uci(), ["N@d6"]);
+
+        // An atomic capture is written like any other.
+        let config = Some(BoardConfig::atomic());
+        let mut board = Board::from_fen("7k/8/2nbp3/3q4/8/8/8/K2R4 w - - 0 1", config).unwrap();
+        assert!(board.play(&Ply::new((3, 7), (3, 3))));
+        assert_eq!(board.history_san(), ["Rxd5"]);
+    }
+
+    #[test]
+    fn display() {
+        use display::*;
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", None).unwrap();
+        assert_eq!(
+            board.to_string(),
+            "8  .  .  .  .  k  .  .  . \n\
+             7  .  .  .  .  .  .  .  . \n\
+             6  .  .  .  .  .  .  .  . \n\
+             5  .  .  .  .  .  .  .  . \n\
+             4  .  .  .  .  .  .  .  . \n\
+             3  .  .  .  .  .  .  .  . \n\
+             2  .  .  .  .  P  .  .  . \n\
+             1  .  .  .  .  K  .  .  . \n\
+             \x20  a  b  c  d  e  f  g  h \n"
+        );
+
+        // The held pawn and where it can go, from black's side.
+        assert!(board.select((4, 6)));
+        let options = RenderOptions {
+            glyphs: Glyphs::Unicode,
+            flipped: true,
+            selection: true,
+            ..RenderOptions::default()
+        };
+        let lines: Vec<String> = board.render(&options).lines().map(String::from).collect();
+        assert_eq!(lines[0], " .  .  .  ♔  .  .  .  . ");
+        assert_eq!(lines[1], " .  .  . [♙] .  .  .  . ");
+        assert_eq!(lines[2], " .  .  . [.] .  .  .  . ");
+        assert_eq!(lines[3], " .  .  . [.] .  .  .  . ");
+        assert_eq!(lines[7], " .  .  .  ♚  .  .  .  . ");
+
+        assert!(board.move_piece((4, 4)));
+        let options = RenderOptions {
+            last_move: true,
+            colours: true,
+            ..RenderOptions::default()
+        };
+        let text = board.render(&options);
+        assert!(text.contains("\x1b[48;5;143;97m P "));
+        assert!(text.contains("\x1b[48;5;180;30m k "));
+        assert_eq!(text.lines().count(), 8);
+    }
+
     #[test]
     fn history() {
         let mut board = Board::new(None);
 
-        for i in 0..100 {
+        for _ in 0..100 {
             assert!(board.select((3, 6)));
             assert!(board.move_piece((3, 4)));
 
@@ -182,16 +1544,27 @@ mod tests {
 
 pub type Point = (usize, usize);
 pub type ChessTile = Option<Piece>;
-pub type BoolGrid = [[bool; 8]; 8];
+pub type BoolGrid = Vec<Vec<bool>>;
 pub type BoardCollection = Vec<Vec<ChessTile>>;
 pub type MoveCollection = HashMap<Point, Box<dyn ChessMove>>;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Team {
     White = 1,
     Black = -1,
 }
 
+impl Team {
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::White => Team::Black,
+            Team::Black => Team::White,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Board {
     pub tiles: BoardCollection,
     pub possible_moves: MoveCollection,
@@ -203,18 +1576,45 @@ pub struct Board {
     pub config: BoardConfig,
     pub history: VecDeque<Box<dyn ChessMove>>,
     pub check: bool,
+    pub pockets: Pockets,
+    // Checks given so far, as the history length right after the checking
+    // move and the side that gave it, kept when the win condition counts them.
+    pub checks_given: Vec<(usize, Team)>,
+    pub clock: Option<Clock>,
+    // How the game ended, once `finished` is set.
+    pub result: Option<GameResult>,
+    // The side whose draw offer is waiting for an answer.
+    pub draw_offer: Option<Team>,
+    // Plies since the last capture, pawn move or drop, and the number of the
+    // move being played, as in FEN.
+    pub halfmove_clock: usize,
+    pub fullmove_number: usize,
+    // The halfmove clock before each move in the history, newest last, to
+    // set it back when the move is taken back.
+    halfmove_clocks: Vec<usize>,
+    // Moves at the back of the history that were set up rather than played,
+    // like the double step a FEN gives for en passant. They can't be taken
+    // back.
+    pub history_start: usize,
+    listeners: Listeners,
 }
 
-static EMPTY_BOOLGRID: BoolGrid = [[false; 8]; 8];
 impl Board {
     pub fn new(configuration: Option<BoardConfig>) -> Board {
-        let configuration = configuration.unwrap_or(BoardConfig::default());
-        let mut tiles = vec![vec![None; 8]; 8];
+        let configuration = configuration.unwrap_or_default();
+        let mut tiles = vec![vec![None; configuration.height]; configuration.width];
 
         (configuration.place_pawns)(&mut tiles);
+        if !configuration.royal_king {
+            for piece in tiles.iter_mut().flatten().flatten() {
+                if piece.name == "King" {
+                    piece.necessity = false;
+                }
+            }
+        }
 
         Self {
-            tiles: tiles,
+            tiles,
             possible_moves: MoveCollection::new(),
             finished: false,
             held_piece: None,
@@ -224,12 +1624,24 @@ impl Board {
             config: configuration,
             history: VecDeque::new(),
             check: false,
+            pockets: Pockets::default(),
+            checks_given: Vec::new(),
+            clock: None,
+            result: None,
+            draw_offer: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            halfmove_clocks: Vec::new(),
+            history_start: 0,
+            listeners: Listeners::default(),
         }
     }
 
     pub fn get_threatened(&self, team: Team) -> BoolGrid {
-        let mut grid = EMPTY_BOOLGRID;
-        let pieces = self.enumerate_pieces(|piece, pos| piece.team != team);
+        let mut grid = vec![vec![false; self.config.height]; self.config.width];
+        let pieces = self.enumerate_pieces(|piece, _| {
+            piece.team != team && !(self.config.atomic && piece.necessity)
+        });
 
         for point in pieces {
             let piece = self.tiles[point.0][point.1].as_ref().unwrap();
@@ -243,6 +1655,25 @@ impl Board {
         grid
     }
 
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.config.width && (y as usize) < self.config.height
+    }
+
+    // The first or last rank, whichever side is looking.
+    pub fn is_last_rank(&self, y: usize) -> bool {
+        y == 0 || y == self.config.height - 1
+    }
+
+    // Pawns of `team` promote on the far end of the board from their side,
+    // which matters where they may stand on their own first rank too.
+    pub fn is_promotion_rank(&self, y: usize, team: Team) -> bool {
+        if self.config.white_stride * (team as i32) < 0 {
+            y == 0
+        } else {
+            y == self.config.height - 1
+        }
+    }
+
     pub fn is_empty(&self, (x, y): Point) -> bool {
         self.tiles[x][y].is_none()
     }
@@ -275,20 +1706,29 @@ impl Board {
     }
 
     pub fn can_promote(&self) -> bool {
-        self.enumerate_pieces(|piece, pos| piece.name == "Pawn" && (pos.1 == 0 || pos.1 == 7))
-            .len()
-            > 0
+        !self
+            .enumerate_pieces(|piece, pos| {
+                piece.name == "Pawn" && self.is_promotion_rank(pos.1, piece.team)
+            })
+            .is_empty()
     }
 
-    pub fn promote(&mut self, into: Piece) {
-        let piece =
-            self.enumerate_pieces(|piece, pos| piece.name == "Pawn" && (pos.1 == 0 || pos.1 == 7));
-        if piece.len() == 0 {
+    pub fn promote(&mut self, mut into: Piece) {
+        let piece = self.enumerate_pieces(|piece, pos| {
+            piece.name == "Pawn" && self.is_promotion_rank(pos.1, piece.team)
+        });
+        if piece.is_empty() {
             return;
         }
 
         let (x, y) = piece[0];
+        into.promoted = true;
         self.tiles[x][y] = Some(into);
+        self.emit(Event::Promotion {
+            team: self.current_enemy,
+            at: (x, y),
+            piece: self.tiles[x][y].as_ref().unwrap(),
+        });
 
         self.update_win_status();
     }
@@ -304,8 +1744,8 @@ impl Board {
 
     fn enumerate_pieces<F: Fn(&Piece, Point) -> bool>(&self, closure: F) -> Vec<Point> {
         let mut ret = Vec::<Point>::new();
-        for i in 0..8 {
-            for j in 0..8 {
+        for i in 0..self.config.width {
+            for j in 0..self.config.height {
                 let piece = self.tiles[i][j].as_ref();
                 if piece.is_none() {
                     continue;
@@ -321,29 +1761,46 @@ impl Board {
         ret
     }
 
-    pub fn get_name(&self, (x, y): Point) -> Option<String> {
-        let piece = self.tiles[x][y].as_ref();
-        if piece.is_none() {
+    // The pawn that has just made a double step, with the square it passed,
+    // while it may still be taken en passant. A double step from the first
+    // rank, which only horde pawns make, can't be taken that way.
+    pub fn en_passant_square(&self) -> Option<(Point, Point)> {
+        let previous = self.history.front()?.as_regular()?;
+        let (from, to) = (previous.from, previous.to);
+        let piece = self.tiles[to.0][to.1].as_ref()?;
+
+        let first_rank = if self.config.white_stride * (piece.team as i32) < 0 {
+            self.config.height - 1
+        } else {
+            0
+        };
+        let distance = (from.1 as i32 - to.1 as i32).abs();
+        if piece.name != "Pawn" || distance != 2 || from.1 == first_rank {
             return None;
         }
 
-        Some(piece.unwrap().name.clone())
+        Some((to, (to.0, (from.1 + to.1) / 2)))
+    }
+
+    pub fn get_name(&self, (x, y): Point) -> Option<String> {
+        let piece = self.tiles[x][y].as_ref()?;
+        Some(piece.name.clone())
     }
 
     pub fn get_selectable(&self) -> Vec<Point> {
-        self.enumerate_pieces(|piece, point| piece.team == self.current_player)
+        self.enumerate_pieces(|piece, _| piece.team == self.current_player)
     }
 
     pub fn get_kings(&self) -> Vec<Point> {
-        self.enumerate_pieces(|piece, point| piece.necessity)
+        self.enumerate_pieces(|piece, _| piece.necessity)
     }
 
     pub fn get_enemies(&self) -> Vec<Point> {
-        self.enumerate_pieces(|piece, (x, y)| piece.team != self.current_player)
+        self.enumerate_pieces(|piece, _| piece.team != self.current_player)
     }
 
     pub fn get_movable(&self) -> Vec<Point> {
-        self.possible_moves.keys().map(|key| *key).collect()
+        self.possible_moves.keys().copied().collect()
     }
 
     pub fn get_attackable(&self) -> Vec<Point> {
@@ -366,10 +1823,68 @@ impl Board {
         }
 
         self.held_piece = Option::from(point);
-        self.possible_moves = piece.get_moves(point, &self, false);
+        self.possible_moves = self.piece_moves(piece, point);
+
+        if self.config.forced_captures && self.can_capture(self.current_player) {
+            let team = self.current_player;
+            let captures: Vec<Point> = self
+                .possible_moves
+                .iter()
+                .filter(|(_, mv)| self.is_capture(mv.as_ref(), team))
+                .map(|(to, _)| *to)
+                .collect();
+
+            self.possible_moves.retain(|to, _| captures.contains(to));
+        }
+
         true
     }
 
+    fn is_capture(&self, mv: &dyn ChessMove, team: Team) -> bool {
+        mv.can_kill()
+            && mv
+                .get_affected_tiles()
+                .iter()
+                .any(|point| self.is_opposite(*point, team))
+    }
+
+    // Whether any piece of `team` can take something, legal or not.
+    pub fn can_capture(&self, team: Team) -> bool {
+        self.enumerate_pieces(|piece, _| piece.team == team)
+            .into_iter()
+            .any(|point| {
+                let piece = self.tiles[point.0][point.1].as_ref().unwrap();
+                self.piece_moves(piece, point)
+                    .values()
+                    .any(|mv| self.is_capture(mv.as_ref(), team))
+            })
+    }
+
+    // The moves of the piece on `point` under the variant's rules: in atomic
+    // chess kings can't capture and captures explode.
+    pub fn piece_moves(&self, piece: &Piece, point: Point) -> MoveCollection {
+        let mut moves = piece.get_moves(point, self, false);
+        if !self.config.atomic {
+            return moves;
+        }
+
+        if piece.necessity {
+            moves.retain(|to, _| !self.is_opposite(*to, piece.team));
+        }
+
+        moves
+            .into_iter()
+            .map(|(to, mv)| {
+                let mv: Box<dyn ChessMove> = if mv.can_kill() {
+                    Box::new(Explosion::new(mv))
+                } else {
+                    mv
+                };
+                (to, mv)
+            })
+            .collect()
+    }
+
     pub fn move_piece(&mut self, to: Point) -> bool {
         if self.held_piece.is_none() {
             return false;
@@ -379,21 +1894,83 @@ impl Board {
             return false;
         }
 
-        self.update_win_status();
+        self.end_turn();
 
         true
     }
 
+    // Everything after a move is on the board: the clock, a draw offer the
+    // move answers, and whether the game is over.
+    fn end_turn(&mut self) {
+        let in_time = self.press_clock();
+        if self.draw_offer == Some(self.current_player) {
+            self.draw_offer = None;
+        }
+
+        self.emit_move();
+
+        // A flag that fell during the move decides the game, and a pawn
+        // waiting to promote is judged once `promote` replaces it.
+        if !in_time {
+            self.check_flag();
+        } else if !self.check_flag() && !self.can_promote() {
+            self.update_win_status();
+        }
+    }
+
+    // How the position ends the game other than by checkmate: an exploded
+    // king, the win condition, or a side without a royal piece, like the
+    // horde, losing its last piece.
+    pub fn decision(&mut self) -> Option<win::Decision> {
+        if let Some(team) = self.exploded_king() {
+            return Some(win::Decision::Win(team.opponent()));
+        }
+
+        let win = self.config.win.clone();
+        if let Some(decision) = win.decide(self) {
+            return Some(decision);
+        }
+
+        for team in [Team::White, Team::Black].iter() {
+            if self
+                .enumerate_pieces(|piece, _| piece.team == *team)
+                .is_empty()
+            {
+                return Some(win::Decision::Win(team.opponent()));
+            }
+        }
+
+        None
+    }
+
     fn update_win_status(&mut self) {
+        if let Some(decision) = self.decision() {
+            self.check = self.exploded_king().is_none() && self.check_check(self.current_player);
+            self.emit_check();
+            let winner = match decision {
+                win::Decision::Win(team) => Some(team),
+                win::Decision::Draw => None,
+            };
+            self.finish(winner, Termination::Variant);
+            return;
+        }
+
         self.check = self.check_check(self.current_player);
-        if self.check && self.check_checkmated() {
-            self.finished = true;
-            self.winner = Some(self.current_enemy);
+        self.emit_check();
+        if self.check_checkmated() {
+            if self.check {
+                self.finish(Some(self.current_enemy), Termination::Checkmate);
+            } else {
+                self.finish(None, Termination::Stalemate);
+            }
         }
 
         if self.check_check(self.current_enemy) {
-            self.finished = true;
-            self.winner = Some(self.current_player);
+            self.finish(Some(self.current_player), Termination::Checkmate);
+        }
+
+        if !self.finished && self.is_dead_position() {
+            self.finish(None, Termination::InsufficientMaterial);
         }
     }
 
@@ -405,14 +1982,21 @@ impl Board {
 
         let mut chessmove = chessmove.unwrap();
         chessmove.perform(&mut self.tiles);
+        self.update_pockets(chessmove.as_ref(), self.current_player, false);
+        self.count_move(chessmove.as_ref());
 
         self.history.push_front(chessmove);
         self.swap_team();
+        self.record_check();
 
         true
     }
 
     fn check_check(&mut self, team: Team) -> bool {
+        if self.config.atomic {
+            return self.atomic_check(team);
+        }
+
         let threat = self.get_threatened(team);
 
         self.get_kings().iter().any(|(x, y)| threat[*x][*y])
@@ -427,8 +2011,8 @@ impl Board {
             for mv in self.get_movable() {
                 self.perform_move(mv);
 
-                let check = self.check_check(self.current_enemy);
-                self.undo_last();
+                let check = self.check_check(self.current_enemy) || !self.allowed_by_win();
+                self.take_back();
 
                 if !check {
                     return false;
@@ -438,23 +2022,177 @@ impl Board {
             }
         }
 
+        for mut mv in self.drop_moves(self.current_player) {
+            mv.perform(&mut self.tiles);
+            self.swap_team();
+            let check = self.check_check(self.current_enemy) || !self.allowed_by_win();
+            self.swap_team();
+            mv.reverse(&mut self.tiles);
+
+            if !check {
+                return false;
+            }
+        }
+
         true
     }
 
-    pub fn undo_last(&mut self) -> bool {
-        let chessmove = self.history.pop_front();
-        if chessmove.is_none() {
+    pub fn get_legal_moves(&mut self) -> Vec<Ply> {
+        self.collect_legal_moves(false)
+    }
+
+    fn collect_legal_moves(&mut self, only_captures: bool) -> Vec<Ply> {
+        let mut plies = Vec::new();
+        let mut captures = Vec::new();
+        let team = self.current_player;
+
+        for from in self.get_selectable() {
+            let piece = self.tiles[from.0][from.1].clone().unwrap();
+
+            for (to, mut mv) in self.piece_moves(&piece, from) {
+                let capture = self.is_capture(mv.as_ref(), team);
+                if only_captures && !capture {
+                    continue;
+                }
+
+                mv.perform(&mut self.tiles);
+                self.swap_team();
+                let legal = !self.check_check(self.current_enemy) && self.allowed_by_win();
+                self.swap_team();
+                // A pawn blown up by its own capture has nothing to promote.
+                let promotes = piece.name == "Pawn"
+                    && self.is_promotion_rank(to.1, team)
+                    && !self.is_empty(to);
+                mv.reverse(&mut self.tiles);
+
+                if !legal {
+                    continue;
+                }
+
+                let list = if capture { &mut captures } else { &mut plies };
+                if promotes {
+                    for name in self.config.promotions.iter() {
+                        list.push(Ply::promoting(from, to, name));
+                    }
+                } else {
+                    list.push(Ply::new(from, to));
+                }
+            }
+        }
+
+        if self.config.forced_captures && !captures.is_empty() {
+            plies.clear();
+        }
+        plies.append(&mut captures);
+
+        if !only_captures {
+            for mut mv in self.drop_moves(self.current_player) {
+                mv.perform(&mut self.tiles);
+                self.swap_team();
+                let legal = !self.check_check(self.current_enemy) && self.allowed_by_win();
+                self.swap_team();
+                mv.reverse(&mut self.tiles);
+
+                if legal {
+                    plies.push(Ply::dropping(&mv.piece.name, mv.to));
+                }
+            }
+        }
+
+        plies.sort();
+        plies
+    }
+
+    // Plays one of the legal moves, anything else is refused.
+    pub fn play(&mut self, ply: &Ply) -> bool {
+        if !self.get_legal_moves().contains(ply) {
             return false;
         }
 
-        chessmove.unwrap().reverse(&mut self.tiles);
+        if let Some(name) = &ply.drop {
+            return self.drop_piece(name, ply.to);
+        }
+
+        if !self.select(ply.from) || !self.move_piece(ply.to) {
+            return false;
+        }
+
+        if let Some(name) = &ply.promotion {
+            let piece = self.config.create_piece(name, self.current_enemy);
+            if piece.is_none() {
+                self.undo_last();
+                return false;
+            }
+
+            self.promote(piece.unwrap());
+        }
+
+        true
+    }
+
+    // Takes back the last move. A clock goes back to the side to move, but
+    // keeps the time as it was after the move.
+    pub fn undo_last(&mut self) -> bool {
+        match self.take_back() {
+            Some(chessmove) => {
+                self.rewind_clock();
+                self.emit(Event::Undo {
+                    mv: chessmove.as_ref(),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    // `undo_last` without telling the listeners, for moves only tried out.
+    fn take_back(&mut self) -> Option<Box<dyn ChessMove>> {
+        if self.history.len() <= self.history_start {
+            return None;
+        }
+
+        let mut chessmove = self.history.pop_front()?;
+        chessmove.reverse(&mut self.tiles);
         self.swap_team();
+        self.update_pockets(chessmove.as_ref(), self.current_player, true);
+
+        self.halfmove_clock = match self.halfmove_clocks.pop() {
+            Some(clock) => clock,
+            None => self.halfmove_clock.saturating_sub(1),
+        };
+        if self.current_player == Team::Black {
+            self.fullmove_number = self.fullmove_number.saturating_sub(1).max(1);
+        }
+
+        let length = self.history.len();
+        self.checks_given.retain(|(at, _)| *at <= length);
         self.deselect();
 
         self.finished = false;
         self.winner = None;
+        self.result = None;
 
-        true
+        Some(chessmove)
+    }
+
+    // Moves the counters on past `chessmove`, once it has been performed by
+    // the side to move.
+    fn count_move(&mut self, chessmove: &dyn ChessMove) {
+        let pawn = chessmove
+            .as_regular()
+            .and_then(|mv| self.get_name(mv.to))
+            .is_some_and(|name| name == "Pawn");
+
+        self.halfmove_clocks.push(self.halfmove_clock);
+        if pawn || chessmove.captured().is_some() || chessmove.as_drop().is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if self.current_player == Team::Black {
+            self.fullmove_number += 1;
+        }
     }
 
     fn swap_team(&mut self) {
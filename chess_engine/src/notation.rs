@@ -0,0 +1,350 @@
+use crate::*;
+
+fn file_letter(x: usize) -> char {
+    (b'a' + x as u8) as char
+}
+
+fn format_square((x, y): Point, height: usize) -> String {
+    format!("{}{}", file_letter(x), height - y)
+}
+
+fn read_square(square: &str, width: usize, height: usize) -> Option<Point> {
+    let mut chars = square.chars();
+    let file = chars.next()?;
+    let rank = chars.as_str().parse::<usize>().ok()?;
+
+    if !file.is_ascii_lowercase() || rank < 1 || rank > height {
+        return None;
+    }
+
+    let x = file as usize - 'a' as usize;
+    if x >= width {
+        return None;
+    }
+
+    Some((x, height - rank))
+}
+
+// Names on the standard 8x8 board, see `Board::square_name` for others.
+pub fn square_name(point: Point) -> String {
+    format_square(point, 8)
+}
+
+pub fn parse_square(square: &str) -> Option<Point> {
+    read_square(square, 8, 8)
+}
+
+impl Board {
+    // Files run on past h and ranks past 8 on larger boards, as in "j10".
+    pub fn square_name(&self, point: Point) -> String {
+        format_square(point, self.config.height)
+    }
+
+    pub fn parse_square(&self, square: &str) -> Option<Point> {
+        read_square(square, self.config.width, self.config.height)
+    }
+}
+
+pub fn piece_letter(name: &str) -> Option<char> {
+    match name {
+        "King" => Some('K'),
+        "Queen" => Some('Q'),
+        "Rook" => Some('R'),
+        "Bishop" => Some('B'),
+        "Knight" => Some('N'),
+        "Pawn" => Some('P'),
+        _ => None,
+    }
+}
+
+pub fn piece_name(letter: char) -> Option<&'static str> {
+    match letter.to_ascii_uppercase() {
+        'K' => Some("King"),
+        'Q' => Some("Queen"),
+        'R' => Some("Rook"),
+        'B' => Some("Bishop"),
+        'N' => Some("Knight"),
+        'P' => Some("Pawn"),
+        _ => None,
+    }
+}
+
+// Castling is either the king moving more than one file or, in Chess960, the
+// king taking its own rook.
+fn is_castling(board: &Board, ply: &Ply, long: bool) -> bool {
+    let king = board.get_name(ply.from).is_some_and(|name| name == "King");
+    let own_rook =
+        board.get_name(ply.to).is_some_and(|name| name == "Rook") && board.is_friendly(ply.to);
+    let distance = ply.to.0 as i32 - ply.from.0 as i32;
+
+    king && (own_rook || distance.abs() > 1) && (distance < 0) == long
+}
+
+// Finds the legal move a SAN string like "Nbd7", "exd6", "e8=Q+", "O-O" or
+// the drop "N@f3" refers to in the current position.
+pub fn parse_san(board: &mut Board, san: &str) -> Option<Ply> {
+    let san = san.trim().trim_end_matches("e.p.").trim_end();
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = board.get_legal_moves();
+
+    match san {
+        "O-O" | "0-0" => return legal.into_iter().find(|ply| is_castling(board, ply, false)),
+        "O-O-O" | "0-0-0" => return legal.into_iter().find(|ply| is_castling(board, ply, true)),
+        _ => {}
+    }
+
+    if let Some(index) = san.find('@') {
+        let name = match san[..index].chars().next() {
+            Some(letter) => board.config.piece_name(letter)?,
+            None => "Pawn".to_owned(),
+        };
+        let to = board.parse_square(&san[index + 1..])?;
+
+        return legal
+            .into_iter()
+            .find(|ply| ply.to == to && ply.drop.as_ref() == Some(&name));
+    }
+
+    let mut chars: Vec<char> = san
+        .chars()
+        .filter(|c| *c != 'x' && *c != ':' && *c != '-')
+        .collect();
+
+    let mut promotion = None;
+    if let Some(index) = chars.iter().position(|c| *c == '=') {
+        promotion = Some(board.config.piece_name(*chars.get(index + 1)?)?);
+        chars.truncate(index);
+    } else if chars.len() > 2
+        && chars[0].is_ascii_lowercase()
+        && chars[chars.len() - 1].is_ascii_uppercase()
+    {
+        promotion = Some(board.config.piece_name(chars.pop()?)?);
+    }
+
+    let name = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => board.config.piece_name(chars.remove(0))?,
+        _ => "Pawn".to_owned(),
+    };
+
+    // The target is the last file letter and the rank after it, anything
+    // before that is a file and/or rank telling pieces apart.
+    let split = chars.iter().rposition(|c| c.is_ascii_lowercase())?;
+    let to: String = chars.split_off(split).into_iter().collect();
+    let to = board.parse_square(&to)?;
+
+    if chars
+        .iter()
+        .any(|c| !c.is_ascii_lowercase() && !c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let file = chars.iter().find(|c| c.is_ascii_lowercase());
+    let rank: String = chars.iter().filter(|c| c.is_ascii_digit()).collect();
+
+    legal.into_iter().find(|ply| {
+        ply.to == to
+            && ply.drop.is_none()
+            && ply.promotion == promotion
+            && board.get_name(ply.from).is_some_and(|piece| piece == name)
+            && file.is_none_or(|file| ply.from.0 == *file as usize - 'a' as usize)
+            && (rank.is_empty() || rank == (board.config.height - ply.from.1).to_string())
+    })
+}
+
+fn letter(board: &Board, name: &str) -> char {
+    board.config.piece_letter(name).unwrap_or('?')
+}
+
+// File, rank or both of the square a piece moves from, as far as needed to
+// tell it from other pieces of its kind that can go to the same square.
+fn disambiguation(board: &Board, ply: &Ply, name: &str, legal: &[Ply]) -> String {
+    let others: Vec<Point> = legal
+        .iter()
+        .filter(|other| {
+            other.to == ply.to
+                && other.from != ply.from
+                && other.drop.is_none()
+                && board.get_name(other.from).as_deref() == Some(name)
+        })
+        .map(|other| other.from)
+        .collect();
+
+    let (x, y) = ply.from;
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|other| other.0 != x) {
+        file_letter(x).to_string()
+    } else if others.iter().all(|other| other.1 != y) {
+        (board.config.height - y).to_string()
+    } else {
+        board.square_name(ply.from)
+    }
+}
+
+// SAN of `ply`, one of the `legal` moves on `board`, which is left as it was.
+fn san(board: &mut Board, ply: &Ply, legal: &[Ply]) -> String {
+    let mut en_passant = false;
+    let mut san = if let Some(name) = &ply.drop {
+        format!("{}@{}", letter(board, name), board.square_name(ply.to))
+    } else if is_castling(board, ply, false) {
+        "O-O".to_owned()
+    } else if is_castling(board, ply, true) {
+        "O-O-O".to_owned()
+    } else {
+        let name = board.get_name(ply.from).unwrap_or_default();
+        let mut san = String::new();
+
+        en_passant = name == "Pawn" && ply.from.0 != ply.to.0 && board.is_empty(ply.to);
+        let capture = en_passant || !board.is_empty(ply.to);
+        if name != "Pawn" {
+            san.push(letter(board, &name));
+            san.push_str(&disambiguation(board, ply, &name, legal));
+        } else if capture {
+            san.push(file_letter(ply.from.0));
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&board.square_name(ply.to));
+
+        if let Some(name) = &ply.promotion {
+            san.push('=');
+            san.push(letter(board, name));
+        }
+
+        san
+    };
+
+    search::make(board, ply);
+    if board.check_check(board.current_player) {
+        san.push(if board.get_legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    board.take_back();
+
+    if en_passant {
+        san.push_str(" e.p.");
+    }
+
+    san
+}
+
+// The SAN of a legal move in the current position, like "Nbd7", "R1a3",
+// "exd6 e.p.", "e8=Q+", "N@f3" or "O-O-O#".
+pub fn format_san(board: &Board, ply: &Ply) -> Option<String> {
+    let mut board = board.clone();
+    let legal = board.get_legal_moves();
+    if !legal.contains(ply) {
+        return None;
+    }
+
+    Some(san(&mut board, ply, &legal))
+}
+
+// Long algebraic notation as UCI writes it: "e2e4", "e7e8q", drops as "N@f3"
+// and castling as the king's move, or as the king taking its own rook in
+// Chess960.
+pub fn format_uci(board: &Board, ply: &Ply) -> String {
+    if let Some(name) = &ply.drop {
+        return format!("{}@{}", letter(board, name), board.square_name(ply.to));
+    }
+
+    let mut uci = board.square_name(ply.from) + &board.square_name(ply.to);
+    if let Some(name) = &ply.promotion {
+        uci.push(letter(board, name).to_ascii_lowercase());
+    }
+
+    uci
+}
+
+pub fn parse_uci(board: &mut Board, uci: &str) -> Option<Ply> {
+    let uci = uci.trim();
+    board
+        .get_legal_moves()
+        .into_iter()
+        .find(|ply| format_uci(board, ply).eq_ignore_ascii_case(uci))
+}
+
+#[derive(PartialEq)]
+struct PieceKey(String, Team);
+
+impl PieceKey {
+    fn of(piece: &Piece) -> Self {
+        PieceKey(piece.name.clone(), piece.team)
+    }
+}
+
+impl Board {
+    // The position the game started from, and for each move since the legal
+    // moves there with the index of the one played. The history only keeps
+    // what a move needs to be taken back, so each one is found by trying the
+    // legal moves until one leaves the same move and piece.
+    pub(crate) fn replay_history(&self) -> (Board, Vec<(usize, Vec<Ply>)>) {
+        let mut board = self.clone();
+        let mut moves = Vec::new();
+
+        while board.history.len() > board.history_start {
+            let mv = board.history.front().unwrap();
+            let record = mv.record();
+            let target = mv.get_target_tile();
+            let piece = board.tiles[target.0][target.1].as_ref().map(PieceKey::of);
+            let before = board.clone();
+            board.undo_last();
+
+            let legal = board.get_legal_moves();
+            let index = legal.iter().position(|ply| {
+                search::make(&mut board, ply);
+                let same = board.history.front().map(|mv| mv.record()) == Some(record.clone())
+                    && board.tiles[target.0][target.1].as_ref().map(PieceKey::of) == piece;
+                board.undo_last();
+                same
+            });
+
+            // A move none of the legal ones matches, which only a history
+            // put together by hand has, is where the game starts instead.
+            match index {
+                Some(index) => moves.push((index, legal)),
+                None => {
+                    board = before;
+                    break;
+                }
+            }
+        }
+
+        moves.reverse();
+        (board, moves)
+    }
+
+    // The moves of the game since the position it started from.
+    pub fn history_plies(&self) -> Vec<Ply> {
+        self.replay_history()
+            .1
+            .into_iter()
+            .map(|(index, mut legal)| legal.swap_remove(index))
+            .collect()
+    }
+
+    pub fn history_san(&self) -> Vec<String> {
+        let (mut board, moves) = self.replay_history();
+        moves
+            .into_iter()
+            .map(|(index, legal)| {
+                let san = san(&mut board, &legal[index], &legal);
+                search::make(&mut board, &legal[index]);
+                san
+            })
+            .collect()
+    }
+
+    pub fn history_uci(&self) -> Vec<String> {
+        self.history_plies()
+            .iter()
+            .map(|ply| format_uci(self, ply))
+            .collect()
+    }
+}
@@ -0,0 +1,201 @@
+use crate::*;
+use notation::parse_san;
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: "*".to_owned(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn winner(&self) -> Option<Team> {
+        match self.result.as_str() {
+            "1-0" => Some(Team::White),
+            "0-1" => Some(Team::Black),
+            _ => None,
+        }
+    }
+
+    // The position the game starts from: the one in the FEN tag when there
+    // is one, else the usual one. None when the FEN doesn't read.
+    pub fn start(&self) -> Option<Board> {
+        match self.tag("FEN") {
+            Some(fen) => Board::from_fen(fen, None),
+            None => Some(Board::new(None)),
+        }
+    }
+
+    // Plays the moves from the starting position, stopping at the first one
+    // that does not parse or is illegal. None are played when the FEN tag
+    // doesn't read.
+    pub fn replay(&self, plies: usize) -> (Board, Vec<Ply>) {
+        let mut board = match self.start() {
+            Some(board) => board,
+            None => return (Board::new(None), Vec::new()),
+        };
+        let mut played = Vec::new();
+
+        for san in self.moves.iter().take(plies) {
+            let ply = parse_san(&mut board, san);
+            if ply.is_none() || !board.play(ply.as_ref().unwrap()) {
+                break;
+            }
+
+            played.push(ply.unwrap());
+        }
+
+        (board, played)
+    }
+}
+
+fn skip_until(chars: &mut Peekable<Chars>, end: char) {
+    for c in chars {
+        if c == end {
+            break;
+        }
+    }
+}
+
+fn skip_variation(chars: &mut Peekable<Chars>) {
+    let mut depth = 1;
+    while depth > 0 {
+        match chars.next() {
+            Some('(') => depth += 1,
+            Some(')') => depth -= 1,
+            Some('{') => skip_until(chars, '}'),
+            Some(_) => {}
+            None => break,
+        }
+    }
+}
+
+fn read_tag(chars: &mut Peekable<Chars>) -> Option<(String, String)> {
+    let mut name = String::new();
+    for c in chars.by_ref() {
+        if c.is_whitespace() {
+            break;
+        }
+
+        name.push(c);
+    }
+
+    skip_until(chars, '"');
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => value.extend(chars.next()),
+            _ => value.push(c),
+        }
+    }
+
+    skip_until(chars, ']');
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name, value))
+}
+
+pub fn parse_pgn(text: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut game = Game::new();
+    let mut in_moves = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '[' => {
+                chars.next();
+                if in_moves {
+                    games.push(std::mem::take(&mut game));
+                    in_moves = false;
+                }
+
+                if let Some(tag) = read_tag(&mut chars) {
+                    if tag.0 == "Result" {
+                        game.result = tag.1.clone();
+                    }
+
+                    game.tags.push(tag);
+                }
+            }
+            '{' => skip_until(&mut chars, '}'),
+            ';' => skip_until(&mut chars, '\n'),
+            '(' => {
+                chars.next();
+                skip_variation(&mut chars);
+            }
+            _ if c.is_whitespace() || c == ')' || c == ']' || c == '}' => {
+                chars.next();
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+
+                    token.push(c);
+                    chars.next();
+                }
+
+                if RESULTS.contains(&token.as_str()) {
+                    game.result = token;
+                    games.push(std::mem::take(&mut game));
+                    in_moves = false;
+                    continue;
+                }
+
+                // Move numbers may be glued to the move, as in "12.e4" or "12...Nf6".
+                let token = match token.rfind('.') {
+                    Some(i) if token[..i].chars().all(|c| c.is_ascii_digit() || c == '.') => {
+                        &token[i + 1..]
+                    }
+                    _ => &token,
+                };
+
+                if token.is_empty() || token.starts_with('$') || token == "e.p." {
+                    continue;
+                }
+
+                game.moves.push(token.to_owned());
+                in_moves = true;
+            }
+        }
+    }
+
+    if in_moves || !game.tags.is_empty() {
+        games.push(game);
+    }
+
+    games
+}
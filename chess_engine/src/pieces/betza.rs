@@ -0,0 +1,243 @@
+use super::custom::*;
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BetzaError {
+    // Character index into the notation where the problem starts.
+    pub position: usize,
+    pub message: String,
+}
+
+impl BetzaError {
+    fn new(position: usize, message: String) -> Self {
+        Self { position, message }
+    }
+}
+
+impl fmt::Display for BetzaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for BetzaError {}
+
+// Base offsets of an atom, and whether it slides without being doubled.
+fn atom(letter: char) -> Option<(Vec<(i32, i32)>, bool)> {
+    let atom = match letter {
+        'W' => (vec![(1, 0)], false),
+        'F' => (vec![(1, 1)], false),
+        'D' => (vec![(2, 0)], false),
+        'N' => (vec![(1, 2)], false),
+        'A' => (vec![(2, 2)], false),
+        'H' => (vec![(3, 0)], false),
+        'C' | 'L' => (vec![(3, 1)], false),
+        'Z' | 'J' => (vec![(3, 2)], false),
+        'G' => (vec![(3, 3)], false),
+        'K' => (vec![(1, 0), (1, 1)], false),
+        'R' => (vec![(1, 0)], true),
+        'B' => (vec![(1, 1)], true),
+        'Q' => (vec![(1, 0), (1, 1)], true),
+        _ => return None,
+    };
+
+    Some(atom)
+}
+
+// Whether a direction modifier, alone or as a pair like "fl" or "ff", picks
+// the offset. Forward is a positive rank as in `Step`.
+fn selects(modifier: &str, (dx, dy): (i32, i32)) -> bool {
+    let narrow = dy.abs() > dx.abs();
+    let wide = dx.abs() > dy.abs();
+
+    match modifier {
+        "f" => dy > 0,
+        "b" => dy < 0,
+        "l" => dx < 0,
+        "r" => dx > 0,
+        "v" => dy != 0 && !wide,
+        "s" => dx != 0 && !narrow,
+        "fl" => dy > 0 && dx < 0,
+        "fr" => dy > 0 && dx > 0,
+        "bl" => dy < 0 && dx < 0,
+        "br" => dy < 0 && dx > 0,
+        "ff" => dy > 0 && narrow,
+        "bb" => dy < 0 && narrow,
+        "ll" => dx < 0 && wide,
+        "rr" => dx > 0 && wide,
+        "fs" => dy > 0 && wide,
+        "bs" => dy < 0 && wide,
+        "lv" => dx < 0 && narrow,
+        "rv" => dx > 0 && narrow,
+        _ => false,
+    }
+}
+
+// Splits direction letters into single modifiers and the pairs Betza reads
+// together, so "fbl" is "f" and "bl".
+fn direction_groups(directions: &str) -> Vec<String> {
+    let chars: Vec<char> = directions.chars().collect();
+    let mut groups = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        if is_pair(&pair) {
+            groups.push(pair);
+            i += 2;
+        } else {
+            groups.push(chars[i].to_string());
+            i += 1;
+        }
+    }
+
+    groups
+}
+
+fn is_pair(pair: &str) -> bool {
+    [
+        "fl", "fr", "bl", "br", "ff", "bb", "ll", "rr", "fs", "bs", "lv", "rv",
+    ]
+    .contains(&pair)
+}
+
+// Parses Betza funny notation such as "WfcF", "NN", "mWcF" or "ifmW2" into
+// movement steps.
+//
+// Atoms are W F D N A H C/L Z/J G plus the shorthands K R B Q. Doubling an
+// atom ("NN") or giving a range ("W4", 0 for unlimited) makes it slide.
+// Before an atom, m and c limit it to moving or capturing, i to the piece's
+// first move, g makes it hop like a grasshopper, and f b l r v s (alone or in
+// pairs like "fl" and "ff") pick directions.
+pub fn parse(notation: &str) -> Result<Vec<Step>, BetzaError> {
+    let chars: Vec<char> = notation.chars().collect();
+    let mut steps = Vec::new();
+    let mut i = 0;
+
+    if chars.is_empty() {
+        return Err(BetzaError::new(0, "empty piece description".to_owned()));
+    }
+
+    while i < chars.len() {
+        let start = i;
+        let mut moves = false;
+        let mut captures = false;
+        let mut initial = false;
+        let mut hop = false;
+        let mut directions = String::new();
+
+        while i < chars.len() && chars[i].is_ascii_lowercase() {
+            match chars[i] {
+                'm' => moves = true,
+                'c' => captures = true,
+                'i' => initial = true,
+                'g' => hop = true,
+                'f' | 'b' | 'l' | 'r' | 'v' | 's' => directions.push(chars[i]),
+                'p' => {
+                    return Err(BetzaError::new(
+                        i,
+                        "hopping over any distance ('p') is not supported, use 'g'".to_owned(),
+                    ))
+                }
+                other => {
+                    return Err(BetzaError::new(i, format!("unknown modifier '{}'", other)));
+                }
+            }
+
+            i += 1;
+        }
+
+        let letter = match chars.get(i) {
+            Some(c) => *c,
+            None => {
+                return Err(BetzaError::new(
+                    start,
+                    "modifiers without a piece letter after them".to_owned(),
+                ))
+            }
+        };
+
+        let (bases, mut slides) = atom(letter)
+            .ok_or_else(|| BetzaError::new(i, format!("unknown piece letter '{}'", letter)))?;
+        i += 1;
+
+        if chars.get(i) == Some(&letter) {
+            slides = true;
+            i += 1;
+        }
+
+        let digits: String = chars[i..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let mut range = None;
+        if !digits.is_empty() {
+            let value = digits
+                .parse::<u32>()
+                .map_err(|_| BetzaError::new(i, format!("range {} is too large", digits)))?;
+            i += digits.len();
+
+            if value != 1 {
+                slides = true;
+                range = Some(value).filter(|value| *value > 0);
+            }
+        }
+
+        let kind = if hop {
+            Kind::Hop
+        } else if slides {
+            Kind::Ride(range)
+        } else {
+            Kind::Leap
+        };
+
+        let mode = match (moves, captures) {
+            (true, false) => Mode::MoveOnly,
+            (false, true) => Mode::CaptureOnly,
+            _ => Mode::Both,
+        };
+
+        let groups = direction_groups(&directions);
+        let mut any = false;
+        for base in bases {
+            let offsets: Vec<(i32, i32)> = all_directions(base)
+                .into_iter()
+                .filter(|offset| groups.is_empty() || groups.iter().any(|g| selects(g, *offset)))
+                .collect();
+
+            if offsets.is_empty() {
+                continue;
+            }
+
+            any = true;
+            steps.push(Step {
+                offsets,
+                kind,
+                mode,
+                initial,
+            });
+        }
+
+        if !any {
+            return Err(BetzaError::new(
+                start,
+                format!("'{}' selects no direction of {}", directions, letter),
+            ));
+        }
+    }
+
+    Ok(steps)
+}
+
+impl PieceDefinition {
+    pub fn from_betza(
+        name: &str,
+        letter: char,
+        worth: i32,
+        notation: &str,
+    ) -> Result<Self, BetzaError> {
+        Ok(Self::new(name, letter, worth, parse(notation)?))
+    }
+}
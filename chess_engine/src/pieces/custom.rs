@@ -0,0 +1,276 @@
+use super::*;
+use crate::chess_move::RegularMove;
+
+use std::sync::Arc;
+
+// Whether a step may move to an empty tile, capture, or both.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Both,
+    MoveOnly,
+    CaptureOnly,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    // Jumps straight to the target, like a knight.
+    Leap,
+    // Slides along the direction until blocked, `range` steps at most.
+    Ride(Option<u32>),
+    // Slides to the first piece in line and lands right behind it, like the
+    // grasshopper.
+    Hop,
+}
+
+// One movement primitive. Offsets are (file, rank) seen from the piece's own
+// side, so a positive rank is forward for both teams.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    pub offsets: Vec<(i32, i32)>,
+    pub kind: Kind,
+    pub mode: Mode,
+    // Only for a piece that has not moved yet, like the pawn's double step.
+    pub initial: bool,
+}
+
+// The offset in every direction it can be turned or mirrored to, eight for
+// a knight, four for a wazir or ferz.
+pub fn all_directions((dx, dy): (i32, i32)) -> Vec<(i32, i32)> {
+    let mut offsets = Vec::new();
+
+    for (a, b) in [(dx, dy), (dy, dx)].iter() {
+        for (sa, sb) in [(1, 1), (1, -1), (-1, 1), (-1, -1)].iter() {
+            let offset = (a * sa, b * sb);
+            if !offsets.contains(&offset) {
+                offsets.push(offset);
+            }
+        }
+    }
+
+    offsets
+}
+
+impl Step {
+    pub fn new(offsets: Vec<(i32, i32)>, kind: Kind) -> Self {
+        Self {
+            offsets,
+            kind,
+            mode: Mode::Both,
+            initial: false,
+        }
+    }
+
+    pub fn leap(offset: (i32, i32)) -> Self {
+        Self::new(all_directions(offset), Kind::Leap)
+    }
+
+    pub fn ride(offset: (i32, i32)) -> Self {
+        Self::new(all_directions(offset), Kind::Ride(None))
+    }
+
+    pub fn hop(offset: (i32, i32)) -> Self {
+        Self::new(all_directions(offset), Kind::Hop)
+    }
+
+    pub fn range(mut self, range: u32) -> Self {
+        if let Kind::Ride(_) = self.kind {
+            self.kind = Kind::Ride(Some(range));
+        }
+
+        self
+    }
+
+    pub fn move_only(mut self) -> Self {
+        self.mode = Mode::MoveOnly;
+        self
+    }
+
+    pub fn capture_only(mut self) -> Self {
+        self.mode = Mode::CaptureOnly;
+        self
+    }
+
+    pub fn initial(mut self) -> Self {
+        self.initial = true;
+        self
+    }
+
+    // Keeps the directions `keep` accepts, as in `forward` for pawn steps.
+    pub fn only<F: Fn((i32, i32)) -> bool>(mut self, keep: F) -> Self {
+        self.offsets.retain(|offset| keep(*offset));
+        self
+    }
+
+    // Adds a move to `to` if the mode allows it there, and tells whether the
+    // tile was empty.
+    fn try_place(
+        &self,
+        from: Point,
+        to: Point,
+        team: Team,
+        board: &Board,
+        only_lethal: bool,
+        collection: &mut MoveCollection,
+    ) -> bool {
+        let empty = board.is_empty(to);
+        let allowed = if empty {
+            // Every tile a capturing step reaches is threatened, even empty.
+            match self.mode {
+                Mode::Both => true,
+                Mode::MoveOnly => !only_lethal,
+                Mode::CaptureOnly => only_lethal,
+            }
+        } else {
+            self.mode != Mode::MoveOnly && board.is_opposite(to, team)
+        };
+
+        if allowed {
+            collection.insert(to, Box::new(RegularMove::new(from, to)));
+        }
+
+        empty
+    }
+
+    fn generate(
+        &self,
+        piece: &Piece,
+        from: Point,
+        board: &Board,
+        only_lethal: bool,
+        collection: &mut MoveCollection,
+    ) {
+        if (only_lethal && self.mode == Mode::MoveOnly) || (self.initial && piece.has_moved) {
+            return;
+        }
+
+        let forward = board.config.white_stride * piece.team as i32;
+
+        for (dx, dy) in self.offsets.iter() {
+            let (dx, dy) = (*dx, dy * forward);
+            let mut x = from.0 as i32;
+            let mut y = from.1 as i32;
+            let mut steps = 0;
+
+            loop {
+                x += dx;
+                y += dy;
+                steps += 1;
+                if !board.in_bounds(x, y) {
+                    break;
+                }
+
+                let to = (x as usize, y as usize);
+                match self.kind {
+                    Kind::Leap => {
+                        self.try_place(from, to, piece.team, board, only_lethal, collection);
+                        break;
+                    }
+                    Kind::Ride(range) => {
+                        let empty =
+                            self.try_place(from, to, piece.team, board, only_lethal, collection);
+                        if !empty || range.is_some_and(|range| steps >= range) {
+                            break;
+                        }
+                    }
+                    Kind::Hop => {
+                        if board.is_empty(to) {
+                            continue;
+                        }
+
+                        if board.in_bounds(x + dx, y + dy) {
+                            let landing = ((x + dx) as usize, (y + dy) as usize);
+                            self.try_place(
+                                from,
+                                landing,
+                                piece.team,
+                                board,
+                                only_lethal,
+                                collection,
+                            );
+                        }
+
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A piece built from movement primitives. Register it in
+// `BoardConfig::pieces` so boards can place, promote to and notate it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PieceDefinition {
+    pub name: String,
+    pub letter: char,
+    pub worth: i32,
+    pub royal: bool,
+    pub steps: Vec<Step>,
+}
+
+impl PieceDefinition {
+    pub fn new(name: &str, letter: char, worth: i32, steps: Vec<Step>) -> Self {
+        Self {
+            name: name.to_owned(),
+            letter: letter.to_ascii_uppercase(),
+            worth,
+            royal: false,
+            steps,
+        }
+    }
+
+    pub fn create(&self, team: Team) -> Piece {
+        let steps = self.steps.clone();
+
+        Piece::new(
+            &self.name,
+            team,
+            self.worth,
+            self.royal,
+            Arc::new(move |piece, point, board, only_lethal| {
+                let mut collection = MoveCollection::new();
+                for step in steps.iter() {
+                    step.generate(piece, point, board, only_lethal, &mut collection);
+                }
+
+                collection
+            }),
+        )
+    }
+}
+
+pub fn archbishop() -> PieceDefinition {
+    PieceDefinition::new(
+        "Archbishop",
+        'A',
+        7,
+        vec![Step::ride((1, 1)), Step::leap((1, 2))],
+    )
+}
+
+pub fn chancellor() -> PieceDefinition {
+    PieceDefinition::new(
+        "Chancellor",
+        'C',
+        8,
+        vec![Step::ride((1, 0)), Step::leap((1, 2))],
+    )
+}
+
+pub fn amazon() -> PieceDefinition {
+    PieceDefinition::new(
+        "Amazon",
+        'M',
+        12,
+        vec![Step::ride((1, 0)), Step::ride((1, 1)), Step::leap((1, 2))],
+    )
+}
+
+pub fn grasshopper() -> PieceDefinition {
+    PieceDefinition::new(
+        "Grasshopper",
+        'G',
+        2,
+        vec![Step::hop((1, 0)), Step::hop((1, 1))],
+    )
+}
@@ -2,16 +2,17 @@ use super::moves::*;
 use super::Piece;
 use crate::*;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub fn pawn(team: Team) -> Piece {
     Piece {
         name: "Pawn".to_owned(),
         necessity: false,
-        team: team,
+        team,
         has_moved: false,
+        promoted: false,
         worth: 1,
-        ptr_getmoves: Rc::new(pawn_moves),
+        ptr_getmoves: Arc::new(pawn_moves),
     }
 }
 
@@ -20,9 +21,10 @@ pub fn king(team: Team) -> Piece {
         name: "King".to_owned(),
         necessity: true,
         has_moved: false,
-        team: team,
+        promoted: false,
+        team,
         worth: 0,
-        ptr_getmoves: Rc::new(king_moves),
+        ptr_getmoves: Arc::new(king_moves),
     }
 }
 
@@ -31,9 +33,10 @@ pub fn bishop(team: Team) -> Piece {
         name: "Bishop".to_owned(),
         necessity: false,
         has_moved: false,
-        team: team,
+        promoted: false,
+        team,
         worth: 3,
-        ptr_getmoves: Rc::new(bishop_moves),
+        ptr_getmoves: Arc::new(bishop_moves),
     }
 }
 
@@ -42,9 +45,10 @@ pub fn rook(team: Team) -> Piece {
         name: "Rook".to_owned(),
         necessity: false,
         has_moved: false,
-        team: team,
+        promoted: false,
+        team,
         worth: 5,
-        ptr_getmoves: Rc::new(rook_moves),
+        ptr_getmoves: Arc::new(rook_moves),
     }
 }
 
@@ -53,9 +57,10 @@ pub fn knight(team: Team) -> Piece {
         name: "Knight".to_owned(),
         necessity: false,
         has_moved: false,
-        team: team,
+        promoted: false,
+        team,
         worth: 3,
-        ptr_getmoves: Rc::new(knight_moves),
+        ptr_getmoves: Arc::new(knight_moves),
     }
 }
 
@@ -64,8 +69,23 @@ pub fn queen(team: Team) -> Piece {
         name: "Queen".to_owned(),
         necessity: false,
         has_moved: false,
-        team: team,
+        promoted: false,
+        team,
         worth: 9,
-        ptr_getmoves: Rc::new(queen_moves),
+        ptr_getmoves: Arc::new(queen_moves),
+    }
+}
+
+pub const PROMOTIONS: [&str; 4] = ["Queen", "Rook", "Bishop", "Knight"];
+
+pub fn by_name(name: &str, team: Team) -> Option<Piece> {
+    match name {
+        "Pawn" => Some(pawn(team)),
+        "King" => Some(king(team)),
+        "Bishop" => Some(bishop(team)),
+        "Rook" => Some(rook(team)),
+        "Knight" => Some(knight(team)),
+        "Queen" => Some(queen(team)),
+        _ => None,
     }
 }
@@ -1,9 +1,11 @@
+pub mod betza;
+pub mod custom;
 pub mod default;
 pub mod moves;
 
 use super::*;
-use std::rc::Rc;
-type MoveGenerator = dyn Fn(&Piece, Point, &Board, bool) -> MoveCollection;
+use std::sync::Arc;
+pub type MoveGenerator = dyn Fn(&Piece, Point, &Board, bool) -> MoveCollection + Send + Sync;
 
 #[derive(Clone)]
 pub struct Piece {
@@ -12,11 +14,32 @@ pub struct Piece {
     pub worth: i32,
     pub necessity: bool,
     pub has_moved: bool,
-    ptr_getmoves: Rc<MoveGenerator>,
+    // Came from a pawn promotion, so it goes back to being a pawn when it is
+    // captured in a drop variant.
+    pub promoted: bool,
+    ptr_getmoves: Arc<MoveGenerator>,
 }
 
 impl Piece {
+    pub fn new(
+        name: &str,
+        team: Team,
+        worth: i32,
+        necessity: bool,
+        moves: Arc<MoveGenerator>,
+    ) -> Self {
+        Self {
+            team,
+            name: name.to_owned(),
+            worth,
+            necessity,
+            has_moved: false,
+            promoted: false,
+            ptr_getmoves: moves,
+        }
+    }
+
     pub fn get_moves(&self, point: Point, board: &Board, only_lethal: bool) -> MoveCollection {
-        (self.ptr_getmoves)(&self, point, &board, only_lethal)
+        (self.ptr_getmoves)(self, point, board, only_lethal)
     }
 }
@@ -1,57 +1,39 @@
 use crate::chess_move::*;
 use crate::*;
 
-use std::cmp::max;
-use std::cmp::min;
-
-fn in_bounds(x: i32, y: i32) -> bool {
-    x >= 0 && x < 8 && y >= 0 && y < 8
-}
-
-fn place_regular(from: Point, (x, y): Point, collection: &mut MoveCollection) {
-    let to = (x as usize, y as usize);
+fn place_regular(from: Point, to: Point, collection: &mut MoveCollection) {
     collection.insert(to, Box::new(RegularMove::new(from, to)));
 }
 
 fn place_enpassant(
     from: Point,
-    mut x: i32,
+    x: i32,
     y: i32,
     stride: i32,
     collection: &mut MoveCollection,
     board: &Board,
 ) {
-    if !in_bounds(x, y + stride) {
+    if !board.in_bounds(x, y + stride) {
         return;
     }
 
     let target = (x as usize, (y + stride) as usize);
     let killpos = (x as usize, y as usize);
 
-    if !board.is_empty(target) || !board.is_enemy(killpos) || board.history.len() == 0 {
+    if !board.is_empty(target) || !board.is_enemy(killpos) {
         return;
     }
 
-    let previous = board.history.front().unwrap().as_regular();
-    if previous.is_none() {
-        return;
-    }
-
-    let previous = previous.unwrap();
-    if previous.to != killpos {
-        return;
-    }
-
-    let piece = board.tiles[previous.to.0][previous.to.1].as_ref().unwrap();
-    let distance = max(previous.from.1, previous.to.1) - min(previous.from.1, previous.to.1);
-
-    if piece.name == "Pawn" && distance == 2 {
+    if board
+        .en_passant_square()
+        .is_some_and(|(pawn, _)| pawn == killpos)
+    {
         collection.insert(target, Box::new(EnPassant::new(from, target, killpos)));
     }
 }
 
 fn place_if_empty(from: Point, x: i32, y: i32, collection: &mut MoveCollection, board: &Board) {
-    if !in_bounds(x, y) {
+    if !board.in_bounds(x, y) {
         return;
     }
 
@@ -69,7 +51,7 @@ fn place_if_enemy(
     collection: &mut MoveCollection,
     board: &Board,
 ) {
-    if !in_bounds(x, y) {
+    if !board.in_bounds(x, y) {
         return;
     }
 
@@ -105,7 +87,7 @@ fn place_beam(
     loop {
         x += dx;
         y += dy;
-        if !in_bounds(x, y) {
+        if !board.in_bounds(x, y) {
             break;
         }
 
@@ -120,7 +102,7 @@ fn place_beam(
     }
 }
 
-pub fn pawn_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -> MoveCollection {
+pub fn pawn_moves(piece: &Piece, pos: Point, board: &Board, _only_lethal: bool) -> MoveCollection {
     let stride = board.config.white_stride * piece.team as i32;
     let mut tiles = MoveCollection::new();
 
@@ -138,8 +120,11 @@ pub fn pawn_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -
     place_if_enemy(pos, piece.team, x - 1, y, &mut tiles, board);
     place_if_enemy(pos, piece.team, x + 1, y, &mut tiles, board);
 
+    // The double step can't jump over a piece right in front.
+    let blocked = !board.in_bounds(x, y) || !board.is_empty((x as usize, y as usize));
+
     let y = y + stride;
-    if !piece.has_moved {
+    if !piece.has_moved && board.config.pawn_double_step && !blocked {
         place_if_empty(pos, x, y, &mut tiles, board);
     }
 
@@ -158,33 +143,30 @@ pub fn king_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -
     }
 
     //castling
-    if !only_lethal && pos.0 == 4 && (pos.1 == 0 || pos.1 == 7) {
+    let back_rank = pos.1 == 0 || pos.1 == board.config.height - 1;
+    if !only_lethal && board.config.castling && !piece.has_moved && back_rank {
         let threatened = board.get_threatened(piece.team);
 
-        for rook in board.enumerate_pieces(|piece, point| {
-            piece.name == "Rook" && piece.team == board.current_player
+        for rook in board.enumerate_pieces(|other, point| {
+            other.name == "Rook" && other.team == piece.team && !other.has_moved && point.1 == pos.1
         }) {
-            if rook.1 != pos.1 || (rook.0 != 0 && rook.0 != 7) {
-                continue;
-            }
+            let mv = Box::new(Castling::new(rook, pos, board.config.width));
 
-            let mut x = pos.0;
-            let mut can_castle = true;
-            while x != rook.0 {
-                if x != pos.0 && (!board.is_empty((x, rook.1)) || threatened[x][rook.1]) {
-                    can_castle = false;
-                    break;
-                }
-
-                if x > rook.0 {
-                    x -= 1;
-                } else {
-                    x += 1;
-                }
+            let blocked = mv
+                .get_path()
+                .iter()
+                .any(|point| *point != pos && *point != rook && !board.is_empty(*point));
+            let attacked = mv.get_king_path().iter().any(|(x, y)| threatened[*x][*y]);
+
+            if blocked || attacked {
+                continue;
             }
 
-            if can_castle {
-                let mv = Box::new(Castling::new(rook, pos));
+            // In Chess960 the king's target can be an ordinary king move, so
+            // castling is entered as the king taking its own rook instead.
+            if board.config.chess960 {
+                tiles.insert(rook, mv);
+            } else {
                 tiles.insert(mv.king_to, mv);
             }
         }
@@ -193,7 +175,7 @@ pub fn king_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -
     tiles
 }
 
-pub fn rook_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -> MoveCollection {
+pub fn rook_moves(piece: &Piece, pos: Point, board: &Board, _only_lethal: bool) -> MoveCollection {
     let mut tiles = MoveCollection::new();
 
     place_beam(pos, piece.team, 1, 0, &mut tiles, board);
@@ -205,7 +187,12 @@ pub fn rook_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -
     tiles
 }
 
-pub fn bishop_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -> MoveCollection {
+pub fn bishop_moves(
+    piece: &Piece,
+    pos: Point,
+    board: &Board,
+    _only_lethal: bool,
+) -> MoveCollection {
     let mut tiles = MoveCollection::new();
 
     place_beam(pos, piece.team, 1, 1, &mut tiles, board);
@@ -216,7 +203,7 @@ pub fn bishop_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool)
     tiles
 }
 
-pub fn queen_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -> MoveCollection {
+pub fn queen_moves(piece: &Piece, pos: Point, board: &Board, _only_lethal: bool) -> MoveCollection {
     let mut tiles = MoveCollection::new();
     place_beam(pos, piece.team, 1, 1, &mut tiles, board);
     place_beam(pos, piece.team, -1, -1, &mut tiles, board);
@@ -231,7 +218,12 @@ pub fn queen_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool)
     tiles
 }
 
-pub fn knight_moves(piece: &Piece, pos: Point, board: &Board, only_lethal: bool) -> MoveCollection {
+pub fn knight_moves(
+    piece: &Piece,
+    pos: Point,
+    board: &Board,
+    _only_lethal: bool,
+) -> MoveCollection {
     let mut tiles = MoveCollection::new();
     let x = pos.0 as i32;
     let y = pos.1 as i32;
@@ -0,0 +1,275 @@
+pub mod keys;
+
+use crate::*;
+use keys::RANDOM64;
+use pgn::Game;
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+
+const PIECE_KINDS: [&str; 6] = ["Pawn", "Knight", "Bishop", "Rook", "Queen", "King"];
+const PROMOTION_KINDS: [&str; 5] = ["", "Knight", "Bishop", "Rook", "Queen"];
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+impl Board {
+    fn castling_right(&self, king: Point, rook: Point, team: Team) -> bool {
+        let unmoved = |(x, y): Point, name: &str| {
+            self.tiles[x][y]
+                .as_ref()
+                .is_some_and(|piece| piece.name == name && piece.team == team && !piece.has_moved)
+        };
+
+        unmoved(king, "King") && unmoved(rook, "Rook")
+    }
+
+    // Zobrist key of the position using the Polyglot book layout, so it can
+    // be looked up in any `.bin` book. Other board sizes all get 0.
+    pub fn polyglot_key(&self) -> u64 {
+        let mut key = 0;
+
+        // Polyglot only describes the standard board.
+        if self.config.width != 8 || self.config.height != 8 {
+            return key;
+        }
+
+        for (x, y) in self.enumerate_pieces(|piece, _| PIECE_KINDS.contains(&piece.name.as_str())) {
+            let piece = self.tiles[x][y].as_ref().unwrap();
+            let kind = PIECE_KINDS
+                .iter()
+                .position(|name| *name == piece.name)
+                .unwrap();
+            let white = if piece.team == Team::White { 1 } else { 0 };
+
+            key ^= RANDOM64[64 * (2 * kind + white) + 8 * (7 - y) + x];
+        }
+
+        let rights = [
+            ((4, 7), (7, 7), Team::White),
+            ((4, 7), (0, 7), Team::White),
+            ((4, 0), (7, 0), Team::Black),
+            ((4, 0), (0, 0), Team::Black),
+        ];
+
+        for (i, (king, rook, team)) in rights.iter().enumerate() {
+            if self.castling_right(*king, *rook, *team) {
+                key ^= RANDOM64[CASTLING_OFFSET + i];
+            }
+        }
+
+        // The en passant file only counts when a pawn is there to take it.
+        if let Some(((x, y), _)) = self.en_passant_square() {
+            let pawn = |x: usize| {
+                x < 8
+                    && self.tiles[x][y].as_ref().is_some_and(|piece| {
+                        piece.name == "Pawn" && piece.team == self.current_player
+                    })
+            };
+
+            if (x > 0 && pawn(x - 1)) || pawn(x + 1) {
+                key ^= RANDOM64[EN_PASSANT_OFFSET + x];
+            }
+        }
+
+        if self.current_player == Team::White {
+            key ^= RANDOM64[TURN_OFFSET];
+        }
+
+        key
+    }
+}
+
+// Castling is stored as the king capturing its own rook.
+pub fn encode_move(board: &Board, ply: &Ply) -> u16 {
+    let mut to = ply.to;
+    let king = board.get_name(ply.from).is_some_and(|name| name == "King");
+    if !board.config.chess960 && king && (ply.to.0 as i32 - ply.from.0 as i32).abs() == 2 {
+        to.0 = if ply.to.0 > ply.from.0 { 7 } else { 0 };
+    }
+
+    let promotion = ply
+        .promotion
+        .as_ref()
+        .and_then(|name| PROMOTION_KINDS.iter().position(|kind| kind == name))
+        .unwrap_or(0);
+
+    (to.0 | (7 - to.1) << 3 | ply.from.0 << 6 | (7 - ply.from.1) << 9 | promotion << 12) as u16
+}
+
+pub fn decode_move(board: &Board, mv: u16) -> Ply {
+    let mv = mv as usize;
+    let from = (mv >> 6 & 7, 7 - (mv >> 9 & 7));
+    let mut to = (mv & 7, 7 - (mv >> 3 & 7));
+
+    let king = board.get_name(from).is_some_and(|name| name == "King");
+    let rook = board.get_name(to).is_some_and(|name| name == "Rook");
+    if !board.config.chess960 && king && rook && board.is_friendly(to) {
+        to.0 = if to.0 > from.0 { 6 } else { 2 };
+    }
+
+    let promotion = match mv >> 12 & 7 {
+        0 => None,
+        kind => PROMOTION_KINDS.get(kind).map(|name| name.to_string()),
+    };
+
+    Ply {
+        from,
+        to,
+        promotion,
+        drop: None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+pub struct Book {
+    pub entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn new(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+        Self { entries }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let entries = bytes
+            .chunks_exact(16)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes([
+                    chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+                ]),
+                mv: u16::from_be_bytes([chunk[8], chunk[9]]),
+                weight: u16::from_be_bytes([chunk[10], chunk[11]]),
+                learn: u32::from_be_bytes([chunk[12], chunk[13], chunk[14], chunk[15]]),
+            })
+            .collect();
+
+        Self::new(entries)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * 16);
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    // Legal book moves for the position together with their weights.
+    pub fn moves(&self, board: &Board) -> Vec<(Ply, u16)> {
+        let key = board.polyglot_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal = board.clone().get_legal_moves();
+
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| (decode_move(board, entry.mv), entry.weight))
+            .filter(|(ply, _)| legal.contains(ply))
+            .collect()
+    }
+
+    // Picks a move with probability proportional to its weight, `roll` being
+    // any random number.
+    pub fn pick(&self, board: &Board, roll: u64) -> Option<Ply> {
+        let moves = self.moves(board);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = roll % total;
+        for (ply, weight) in moves {
+            if roll < weight as u64 {
+                return Some(ply);
+            }
+
+            roll -= weight as u64;
+        }
+
+        None
+    }
+
+    pub fn pick_random(&self, board: &Board) -> Option<Ply> {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(board.polyglot_key());
+        self.pick(board, hasher.finish())
+    }
+
+    // Counts every move played in the first `max_ply` plies of the games and
+    // keeps those seen in at least `min_games` of them. A move is weighted by
+    // the points the side playing it scored: two for a win, one for a draw.
+    // A game that plays the same move in the same position twice counts once.
+    pub fn build(games: &[Game], min_games: u32, max_ply: usize) -> Self {
+        let mut stats: HashMap<(u64, u16), (u32, u32)> = HashMap::new();
+
+        for game in games {
+            let mut board = Board::new(None);
+            let mut seen = HashSet::new();
+
+            for san in game.moves.iter().take(max_ply) {
+                let ply = notation::parse_san(&mut board, san);
+                if ply.is_none() {
+                    break;
+                }
+
+                let ply = ply.unwrap();
+                let points = match game.winner() {
+                    Some(team) if team == board.current_player => 2,
+                    Some(_) => 0,
+                    None if game.result == "1/2-1/2" => 1,
+                    None => 0,
+                };
+
+                let key = (board.polyglot_key(), encode_move(&board, &ply));
+                if seen.insert(key) {
+                    let stat = stats.entry(key).or_insert((0, 0));
+                    stat.0 += 1;
+                    stat.1 += points;
+                }
+
+                if !board.play(&ply) {
+                    break;
+                }
+            }
+        }
+
+        let entries = stats
+            .into_iter()
+            .filter(|(_, (count, _))| *count >= min_games)
+            .map(|((key, mv), (_, points))| BookEntry {
+                key,
+                mv,
+                weight: points.min(u16::MAX as u32) as u16,
+                learn: 0,
+            })
+            .collect();
+
+        Self::new(entries)
+    }
+}
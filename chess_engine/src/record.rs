@@ -0,0 +1,278 @@
+use crate::*;
+use clock::{ClockState, RealTime};
+use drops::Pockets;
+use result::GameResult;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+// A piece as plain data. Its move generator is looked up again by name in
+// the board configuration when it is restored.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PieceRecord {
+    pub team: Team,
+    pub name: String,
+    pub worth: i32,
+    pub necessity: bool,
+    pub has_moved: bool,
+    pub promoted: bool,
+}
+
+impl PieceRecord {
+    pub fn of(piece: &Piece) -> Self {
+        Self {
+            team: piece.team,
+            name: piece.name.clone(),
+            worth: piece.worth,
+            necessity: piece.necessity,
+            has_moved: piece.has_moved,
+            promoted: piece.promoted,
+        }
+    }
+
+    pub fn restore(&self, config: &BoardConfig) -> Option<Piece> {
+        let mut piece = config.create_piece(&self.name, self.team)?;
+        piece.worth = self.worth;
+        piece.necessity = self.necessity;
+        piece.has_moved = self.has_moved;
+        piece.promoted = self.promoted;
+        Some(piece)
+    }
+}
+
+fn record_piece(piece: &Option<Piece>) -> Option<PieceRecord> {
+    piece.as_ref().map(PieceRecord::of)
+}
+
+// Restores an optional piece, failing only when one was recorded and can't
+// be made again.
+fn restore_piece(record: &Option<PieceRecord>, config: &BoardConfig) -> Option<Option<Piece>> {
+    match record {
+        Some(record) => record.restore(config).map(Some),
+        None => Some(None),
+    }
+}
+
+// A move from the history as plain data, with what it has to remember to be
+// taken back.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MoveRecord {
+    Regular {
+        from: Point,
+        to: Point,
+        prev_state: Option<PieceRecord>,
+        killed: Option<PieceRecord>,
+    },
+    EnPassant {
+        from: Point,
+        to: Point,
+        killpos: Point,
+        killed: Option<PieceRecord>,
+    },
+    Castling {
+        rook_from: Point,
+        rook_to: Point,
+        king_from: Point,
+        king_to: Point,
+        king: Option<PieceRecord>,
+        rook: Option<PieceRecord>,
+    },
+    Drop {
+        piece: PieceRecord,
+        to: Point,
+    },
+    Explosion {
+        inner: Box<MoveRecord>,
+        exploded: Vec<(Point, PieceRecord)>,
+    },
+}
+
+impl MoveRecord {
+    pub fn regular(mv: &RegularMove) -> Self {
+        MoveRecord::Regular {
+            from: mv.from,
+            to: mv.to,
+            prev_state: record_piece(&mv.prev_state),
+            killed: record_piece(&mv.killed),
+        }
+    }
+
+    pub fn en_passant(mv: &EnPassant) -> Self {
+        MoveRecord::EnPassant {
+            from: mv.from,
+            to: mv.to,
+            killpos: mv.killpos,
+            killed: record_piece(&mv.killed),
+        }
+    }
+
+    pub fn castling(mv: &Castling) -> Self {
+        MoveRecord::Castling {
+            rook_from: mv.rook_from,
+            rook_to: mv.rook_to,
+            king_from: mv.king_from,
+            king_to: mv.king_to,
+            king: record_piece(&mv.king),
+            rook: record_piece(&mv.rook),
+        }
+    }
+
+    pub fn restore(&self, config: &BoardConfig) -> Option<Box<dyn ChessMove>> {
+        let mv: Box<dyn ChessMove> = match self {
+            MoveRecord::Regular {
+                from,
+                to,
+                prev_state,
+                killed,
+            } => {
+                let mut mv = RegularMove::new(*from, *to);
+                mv.prev_state = restore_piece(prev_state, config)?;
+                mv.killed = restore_piece(killed, config)?;
+                Box::new(mv)
+            }
+            MoveRecord::EnPassant {
+                from,
+                to,
+                killpos,
+                killed,
+            } => {
+                let mut mv = EnPassant::new(*from, *to, *killpos);
+                mv.killed = restore_piece(killed, config)?;
+                Box::new(mv)
+            }
+            MoveRecord::Castling {
+                rook_from,
+                rook_to,
+                king_from,
+                king_to,
+                king,
+                rook,
+            } => Box::new(Castling {
+                rook_from: *rook_from,
+                rook_to: *rook_to,
+                king_from: *king_from,
+                king_to: *king_to,
+                king: restore_piece(king, config)?,
+                rook: restore_piece(rook, config)?,
+            }),
+            MoveRecord::Drop { piece, to } => Box::new(DropMove::new(piece.restore(config)?, *to)),
+            MoveRecord::Explosion { inner, exploded } => {
+                let mut mv = Explosion::new(inner.restore(config)?);
+                for (point, piece) in exploded {
+                    mv.exploded.push((*point, piece.restore(config)?));
+                }
+                Box::new(mv)
+            }
+        };
+
+        Some(mv)
+    }
+}
+
+// Everything a game is made of apart from its rules, which are given again
+// as the configuration when it is restored. The rules a FEN can turn on are
+// kept with it, as a FEN alone would bring them back. The history is oldest
+// first.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameState {
+    pub chess960: bool,
+    pub drops: bool,
+    // The name of the win condition, as in variant files.
+    pub win: String,
+    pub tiles: Vec<Vec<Option<PieceRecord>>>,
+    pub current_player: Team,
+    pub check: bool,
+    pub history: Vec<MoveRecord>,
+    pub pockets: Pockets,
+    pub checks_given: Vec<(usize, Team)>,
+    pub clock: Option<ClockState>,
+    pub result: Option<GameResult>,
+    pub draw_offer: Option<Team>,
+    pub halfmove_clock: usize,
+    pub fullmove_number: usize,
+    // The halfmove clock before each move in the history.
+    pub halfmove_clocks: Vec<usize>,
+    // How many of the oldest moves were set up rather than played.
+    pub history_start: usize,
+}
+
+impl Board {
+    pub fn to_state(&self) -> GameState {
+        GameState {
+            chess960: self.config.chess960,
+            drops: self.config.drops,
+            win: self.config.win.name().to_owned(),
+            tiles: self
+                .tiles
+                .iter()
+                .map(|column| column.iter().map(record_piece).collect())
+                .collect(),
+            current_player: self.current_player,
+            check: self.check,
+            history: self.history.iter().rev().map(|mv| mv.record()).collect(),
+            pockets: self.pockets.clone(),
+            checks_given: self.checks_given.clone(),
+            clock: self.clock.as_ref().map(|clock| clock.state()),
+            result: self.result,
+            draw_offer: self.draw_offer,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            halfmove_clocks: self.halfmove_clocks.clone(),
+            history_start: self.history_start,
+        }
+    }
+
+    // The board `state` was taken from, or None when it doesn't fit the
+    // configuration. A running clock goes on from the time it had, measured
+    // with `RealTime`.
+    pub fn from_state(state: &GameState, configuration: Option<BoardConfig>) -> Option<Board> {
+        let mut board = Board::new(configuration);
+        board.config.chess960 = state.chess960;
+        board.config.drops = state.drops;
+        // A win condition of its own on the configuration is kept when it has
+        // the saved name, as only the built-in ones can be found by name.
+        if board.config.win.name() != state.win {
+            board.config.win = win::by_name(&state.win)?;
+        }
+
+        let (width, height) = (board.config.width, board.config.height);
+        if state.tiles.len() != width || state.tiles.iter().any(|column| column.len() != height) {
+            return None;
+        }
+
+        for (x, column) in state.tiles.iter().enumerate() {
+            for (y, tile) in column.iter().enumerate() {
+                board.tiles[x][y] = restore_piece(tile, &board.config)?;
+            }
+        }
+
+        for record in state.history.iter() {
+            board.history.push_front(record.restore(&board.config)?);
+        }
+
+        board.current_player = state.current_player;
+        board.current_enemy = state.current_player.opponent();
+        board.check = state.check;
+        board.pockets = state.pockets.clone();
+        board.checks_given = state.checks_given.clone();
+        board.clock = state
+            .clock
+            .as_ref()
+            .map(|clock| Clock::from_state(clock, Arc::new(RealTime::new())));
+        board.result = state.result;
+        board.finished = state.result.is_some();
+        board.winner = state.result.and_then(|result| result.winner);
+        board.draw_offer = state.draw_offer;
+        board.halfmove_clock = state.halfmove_clock;
+        board.fullmove_number = state.fullmove_number;
+        board.halfmove_clocks = state.halfmove_clocks.clone();
+        board.history_start = state.history_start.min(board.history.len());
+
+        Some(board)
+    }
+}
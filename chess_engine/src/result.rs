@@ -0,0 +1,169 @@
+use crate::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Why a game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    Timeout,
+    Agreement,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    Abandonment,
+    // Decided by a rule of the variant, such as a third check or an
+    // exploded king.
+    Variant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameResult {
+    pub winner: Option<Team>,
+    pub termination: Termination,
+}
+
+impl GameResult {
+    // The result as PGN writes it.
+    pub fn score(&self) -> &'static str {
+        match self.winner {
+            Some(Team::White) => "1-0",
+            Some(Team::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl Board {
+    pub(crate) fn finish(&mut self, winner: Option<Team>, termination: Termination) {
+        self.finished = true;
+        self.winner = winner;
+        self.result = Some(GameResult {
+            winner,
+            termination,
+        });
+        self.draw_offer = None;
+
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+        }
+
+        self.emit(Event::GameOver(self.result.unwrap()));
+    }
+
+    pub fn resign(&mut self, team: Team) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        self.finish(Some(team.opponent()), Termination::Resignation);
+        true
+    }
+
+    // Ends the game for a player who left it, as a server would.
+    pub fn abandon(&mut self, team: Team) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        self.finish(Some(team.opponent()), Termination::Abandonment);
+        true
+    }
+
+    // The offer stands until the other side accepts or declines it, or
+    // answers it with a move.
+    pub fn offer_draw(&mut self, team: Team) -> bool {
+        if self.finished || self.draw_offer.is_some() {
+            return false;
+        }
+
+        self.draw_offer = Some(team);
+        true
+    }
+
+    pub fn accept_draw(&mut self, team: Team) -> bool {
+        if self.finished || self.draw_offer != Some(team.opponent()) {
+            return false;
+        }
+
+        self.finish(None, Termination::Agreement);
+        true
+    }
+
+    pub fn decline_draw(&mut self, team: Team) -> bool {
+        if self.draw_offer != Some(team.opponent()) {
+            return false;
+        }
+
+        self.draw_offer = None;
+        true
+    }
+
+    // Neither side can ever mate under the standard rules: nothing but the
+    // kings and at most one bishop or knight.
+    pub fn is_dead_position(&self) -> bool {
+        let config = &self.config;
+        if config.atomic || config.drops || !config.royal_king || config.win.name() != "checkmate" {
+            return false;
+        }
+
+        let pieces = self.enumerate_pieces(|piece, _| !piece.necessity);
+        match pieces.as_slice() {
+            [] => true,
+            [(x, y)] => {
+                let name = &self.tiles[*x][*y].as_ref().unwrap().name;
+                name == "Bishop" || name == "Knight"
+            }
+            _ => false,
+        }
+    }
+
+    // Draws the game when the current position has now appeared three times
+    // or fifty moves have gone by without a capture or pawn move.
+    pub fn claim_draw(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        let (repetitions, halfmoves) = self.repetitions_and_halfmoves();
+        if repetitions >= 3 {
+            self.finish(None, Termination::Repetition);
+        } else if halfmoves >= 100 {
+            self.finish(None, Termination::FiftyMoves);
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    // How often the current position has appeared since the last capture,
+    // pawn move or drop, and the halfmove clock. The repetitions are found by
+    // taking the moves since then back on a copy.
+    fn repetitions_and_halfmoves(&self) -> (usize, usize) {
+        let key = |board: &Board| -> String {
+            let fen = board.to_fen();
+            fen.split(' ').take(4).collect::<Vec<_>>().join(" ")
+        };
+
+        let mut board = self.clone();
+        board.clock = None;
+        let current = key(&board);
+        let mut repetitions = 1;
+        let halfmoves = self.halfmove_clock;
+
+        for _ in 0..halfmoves.min(self.history.len()) {
+            board.undo_last();
+            if key(&board) == current {
+                repetitions += 1;
+            }
+        }
+
+        (repetitions, halfmoves)
+    }
+}
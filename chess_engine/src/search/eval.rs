@@ -0,0 +1,68 @@
+use crate::*;
+
+// Distance from the edge on a line `size` tiles long, at most 3 on 8 tiles.
+fn centrality(coordinate: usize, size: usize) -> i32 {
+    let last = size as i32 - 1;
+    (last - (2 * coordinate as i32 - last).abs()) / 2
+}
+
+fn piece_value(piece: &Piece, (x, y): Point, board: &Board) -> i32 {
+    let center = centrality(x, board.config.width) + centrality(y, board.config.height);
+
+    let bonus = match piece.name.as_str() {
+        "Pawn" => {
+            let home = if board.config.white_stride * piece.team as i32 > 0 {
+                0
+            } else {
+                board.config.height - 1
+            };
+
+            6 * (home as i32 - y as i32).abs() + center
+        }
+        "Knight" | "Bishop" => 5 * center,
+        "Rook" | "Queen" => 2 * center,
+        _ => 0,
+    };
+
+    piece.worth * 100 + bonus
+}
+
+// Static score of the position in centipawns, seen from the side to move.
+pub fn evaluate(board: &Board) -> i32 {
+    let mut score = 0;
+
+    for (x, column) in board.tiles.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if let Some(piece) = tile {
+                let value = piece_value(piece, (x, y), board);
+                if piece.team == board.current_player {
+                    score += value;
+                } else {
+                    score -= value;
+                }
+            }
+        }
+    }
+
+    // Pieces in hand count as material in drop variants.
+    for team in [board.current_player, board.current_enemy].iter() {
+        for name in board.pockets.get(*team) {
+            let worth = board
+                .config
+                .create_piece(name, *team)
+                .map_or(0, |piece| piece.worth * 100);
+
+            if *team == board.current_player {
+                score += worth;
+            } else {
+                score -= worth;
+            }
+        }
+    }
+
+    if board.config.win.losing() {
+        -score
+    } else {
+        score
+    }
+}
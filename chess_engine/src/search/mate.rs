@@ -0,0 +1,165 @@
+use super::make;
+use super::tt::position_key;
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct MateSolution {
+    // Every first move that forces mate, with the number of moves it needs.
+    pub solutions: Vec<(Ply, u32)>,
+    // The quickest mate against the longest defence.
+    pub pv: Vec<Ply>,
+}
+
+impl MateSolution {
+    pub fn is_mate(&self) -> bool {
+        !self.solutions.is_empty()
+    }
+
+    // A composed problem is cooked when more than one key move works.
+    pub fn is_cooked(&self) -> bool {
+        self.solutions.len() > 1
+    }
+}
+
+// Proves or disproves that the side to move mates within `moves` moves.
+pub fn solve_mate(board: &Board, moves: u32) -> MateSolution {
+    let mut solver = MateSolver {
+        board: board.clone(),
+        known: HashMap::new(),
+    };
+
+    let mut solutions = Vec::new();
+    for first in solver.board.get_legal_moves() {
+        make(&mut solver.board, &first);
+        let distance = (1..=moves).find(|n| solver.defender_loses(n - 1));
+        solver.board.undo_last();
+
+        if let Some(distance) = distance {
+            solutions.push((first, distance));
+        }
+    }
+
+    solutions.sort_by_key(|(_, distance)| *distance);
+
+    let pv = match solutions.first() {
+        Some((first, distance)) => {
+            make(&mut solver.board, first);
+            let mut pv = vec![first.clone()];
+            solver.defender_line(distance - 1, &mut pv);
+            pv
+        }
+        None => Vec::new(),
+    };
+
+    MateSolution { solutions, pv }
+}
+
+struct MateSolver {
+    board: Board,
+    known: HashMap<(u64, u32, bool), bool>,
+}
+
+impl MateSolver {
+    fn mated(&mut self) -> bool {
+        let current = self.board.current_player;
+        self.board.check_check(current)
+    }
+
+    fn attacker_wins(&mut self, moves: u32) -> bool {
+        if moves == 0 {
+            return false;
+        }
+
+        let key = (position_key(&self.board), moves, true);
+        if let Some(known) = self.known.get(&key) {
+            return *known;
+        }
+
+        let mut wins = false;
+        for mv in self.board.get_legal_moves() {
+            make(&mut self.board, &mv);
+            wins = self.defender_loses(moves - 1);
+            self.board.undo_last();
+
+            if wins {
+                break;
+            }
+        }
+
+        self.known.insert(key, wins);
+        wins
+    }
+
+    // Defender to move, the attacker has `moves` moves left after this one.
+    fn defender_loses(&mut self, moves: u32) -> bool {
+        let key = (position_key(&self.board), moves, false);
+        if let Some(known) = self.known.get(&key) {
+            return *known;
+        }
+
+        let replies = self.board.get_legal_moves();
+        let loses = if replies.is_empty() {
+            self.mated()
+        } else if moves == 0 {
+            false
+        } else {
+            let mut loses = true;
+            for mv in replies {
+                make(&mut self.board, &mv);
+                loses = self.attacker_wins(moves);
+                self.board.undo_last();
+
+                if !loses {
+                    break;
+                }
+            }
+
+            loses
+        };
+
+        self.known.insert(key, loses);
+        loses
+    }
+
+    fn quickest(&mut self, moves: u32) -> u32 {
+        (1..=moves)
+            .find(|n| self.attacker_wins(*n))
+            .unwrap_or(moves)
+    }
+
+    // Extends the line with the reply that holds out longest, leaving the
+    // board unchanged afterwards.
+    fn defender_line(&mut self, moves: u32, pv: &mut Vec<Ply>) {
+        let mut longest: Option<(Ply, u32)> = None;
+        for mv in self.board.get_legal_moves() {
+            make(&mut self.board, &mv);
+            let distance = self.quickest(moves);
+            self.board.undo_last();
+
+            if longest.as_ref().is_none_or(|(_, best)| distance > *best) {
+                longest = Some((mv, distance));
+            }
+        }
+
+        if let Some((mv, distance)) = longest {
+            make(&mut self.board, &mv);
+            pv.push(mv);
+            self.attacker_line(distance, pv);
+            self.board.undo_last();
+        }
+    }
+
+    fn attacker_line(&mut self, moves: u32, pv: &mut Vec<Ply>) {
+        for mv in self.board.get_legal_moves() {
+            make(&mut self.board, &mv);
+            if self.defender_loses(moves - 1) {
+                pv.push(mv);
+                self.defender_line(moves - 1, pv);
+                self.board.undo_last();
+                return;
+            }
+
+            self.board.undo_last();
+        }
+    }
+}
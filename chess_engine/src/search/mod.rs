@@ -0,0 +1,599 @@
+pub mod eval;
+pub mod mate;
+pub mod time;
+pub mod tt;
+
+use crate::*;
+use eval::evaluate;
+use tablebase::{Tablebase, Wdl};
+use time::TimeManager;
+use tt::*;
+use win::Decision;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const MATE: i32 = 30000;
+pub const INFINITY: i32 = 32000;
+pub const MAX_DEPTH: u32 = 64;
+
+const MATE_BOUND: i32 = MATE - 1000;
+const FAIL_LOW_MARGIN: i32 = 30;
+
+pub type SearchListener = Box<dyn Fn(&SearchInfo) + Send + Sync>;
+
+pub struct SearchConfig {
+    pub depth: u32,
+    pub threads: usize,
+    pub hash_entries: usize,
+    pub time: Option<TimeManager>,
+    pub multi_pv: usize,
+    pub listener: Option<SearchListener>,
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            threads: 1,
+            hash_entries: 1 << 16,
+            time: None,
+            multi_pv: 1,
+            listener: None,
+            tablebase: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    Centipawns(i32),
+    // Moves until mate, negative when the side to move is the one getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_internal(score: i32) -> Self {
+        if score > MATE_BOUND {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score < -MATE_BOUND {
+            Score::Mate(-(MATE + score + 1) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PvLine {
+    pub score: Score,
+    pub pv: Vec<Ply>,
+}
+
+// Sent to the listener after every completed iteration.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub lines: Vec<PvLine>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Option<Ply>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Ply>,
+    pub lines: Vec<PvLine>,
+}
+
+pub fn search(board: &Board, config: &SearchConfig) -> SearchResult {
+    let table = TranspositionTable::new(config.hash_entries);
+    search_with_table(board, config, &table)
+}
+
+// Lazy SMP: every thread searches the same root on its own copy of the board,
+// and the helpers only contribute by filling the shared table. The reported
+// result always comes from thread 0, so a single thread is fully deterministic.
+pub fn search_with_table(
+    board: &Board,
+    config: &SearchConfig,
+    table: &TranspositionTable,
+) -> SearchResult {
+    let stop = AtomicBool::new(false);
+    let depth = config.depth.max(1);
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..config.threads.max(1))
+            .map(|id| {
+                let mut board = board.clone();
+                let stop = &stop;
+
+                scope.spawn(move || {
+                    let mut worker = Worker::new(id, table, stop);
+                    worker.tablebase = config.tablebase.as_deref();
+                    worker.iterate(&mut board, depth);
+                    worker.nodes
+                })
+            })
+            .collect();
+
+        let mut worker = Worker::new(0, table, &stop);
+        worker.multi_pv = config.multi_pv.max(1);
+        worker.listener = config.listener.as_ref();
+        worker.tablebase = config.tablebase.as_deref();
+        worker.timer = config.time.clone();
+        if let Some(timer) = worker.timer.as_mut() {
+            timer.start();
+        }
+
+        let mut result = worker.iterate(&mut board.clone(), depth);
+        stop.store(true, Ordering::Relaxed);
+
+        for helper in helpers {
+            result.nodes += helper.join().unwrap_or(0);
+        }
+
+        result
+    })
+}
+
+// Drops to the same tile are told apart by the same code, pawns being the
+// ones outside the promotions.
+fn promotion_code(board: &Board, ply: &Ply) -> u8 {
+    match ply.promotion.as_ref().or(ply.drop.as_ref()) {
+        Some(name) => board
+            .config
+            .promotions
+            .iter()
+            .position(|p| p == name)
+            .map_or(u8::MAX, |i| (i + 1).min(254) as u8),
+        None => 0,
+    }
+}
+
+// Score of a position the variant's win condition has already decided.
+fn decided(board: &mut Board, ply: u32) -> Option<i32> {
+    board.decision().map(|decision| match decision {
+        Decision::Win(team) if team == board.current_player => MATE - ply as i32,
+        Decision::Win(_) => -MATE + ply as i32,
+        Decision::Draw => 0,
+    })
+}
+
+fn to_table(score: i32, ply: u32) -> i32 {
+    if score > MATE_BOUND {
+        score + ply as i32
+    } else if score < -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: u32) -> i32 {
+    if score > MATE_BOUND {
+        score - ply as i32
+    } else if score < -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+pub(crate) fn make(board: &mut Board, ply: &Ply) {
+    if let Some(name) = &ply.drop {
+        board.perform_drop(name, ply.to);
+        return;
+    }
+
+    board.select(ply.from);
+    board.perform_move(ply.to);
+
+    if let Some(name) = &ply.promotion {
+        let mut piece = board.config.create_piece(name, board.current_enemy);
+        if let Some(piece) = piece.as_mut() {
+            piece.promoted = true;
+        }
+
+        board.tiles[ply.to.0][ply.to.1] = piece;
+    }
+}
+
+struct Worker<'a> {
+    id: usize,
+    table: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    nodes: u64,
+    timer: Option<TimeManager>,
+    multi_pv: usize,
+    listener: Option<&'a SearchListener>,
+    tablebase: Option<&'a Tablebase>,
+    started: Instant,
+}
+
+impl<'a> Worker<'a> {
+    fn new(id: usize, table: &'a TranspositionTable, stop: &'a AtomicBool) -> Self {
+        Self {
+            id,
+            table,
+            stop,
+            nodes: 0,
+            timer: None,
+            multi_pv: 1,
+            listener: None,
+            tablebase: None,
+            started: Instant::now(),
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    // Only the main thread keeps time. The hard limit holds from the first
+    // node, `iterate` falls back on any legal move when nothing finished.
+    fn check_time(&self) {
+        if !self.nodes.is_multiple_of(256) {
+            return;
+        }
+
+        if self
+            .timer
+            .as_ref()
+            .is_some_and(|timer| timer.hard_expired())
+        {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn iterate(&mut self, board: &mut Board, depth: u32) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        // Odd helpers start one ply deeper so the threads drift apart, and
+        // have nothing to do in a search only one ply deep.
+        let start = 1 + (self.id % 2) as u32;
+        for current in start..=depth {
+            let mut lines: Vec<(i32, Vec<Ply>)> = Vec::new();
+            let mut excluded = Vec::new();
+
+            while lines.len() < self.multi_pv {
+                let line = self.root_search(board, current, &excluded);
+                if self.stopped() {
+                    break;
+                }
+
+                match line {
+                    Some((score, pv)) => {
+                        excluded.extend(pv.first().cloned());
+                        lines.push((score, pv));
+                    }
+                    None => break,
+                }
+
+                if excluded.is_empty() {
+                    break;
+                }
+            }
+
+            if self.stopped() || lines.is_empty() {
+                break;
+            }
+
+            lines.sort_by_key(|(score, _)| -score);
+            let (score, pv) = lines[0].clone();
+
+            if let Some(timer) = self.timer.as_mut() {
+                if result.depth > 0 && score < result.score - FAIL_LOW_MARGIN {
+                    timer.on_fail_low();
+                } else if result.depth > 0 && pv.first() != result.best_move.as_ref() {
+                    timer.on_best_move_change();
+                } else {
+                    timer.on_stable_iteration();
+                }
+            }
+
+            result.best_move = pv.first().cloned();
+            result.score = score;
+            result.depth = current;
+            result.pv = pv;
+            result.lines = lines
+                .into_iter()
+                .map(|(score, pv)| PvLine {
+                    score: Score::from_internal(score),
+                    pv,
+                })
+                .collect();
+
+            if let Some(listener) = self.listener {
+                listener(&SearchInfo {
+                    depth: current,
+                    nodes: self.nodes,
+                    elapsed: self.started.elapsed(),
+                    lines: result.lines.clone(),
+                });
+            }
+
+            if !self
+                .timer
+                .as_ref()
+                .is_none_or(|timer| timer.can_start_iteration())
+            {
+                break;
+            }
+        }
+
+        // Out of time before the first iteration finished: any legal move is
+        // better than losing on time, and the table may know a good one.
+        if self.id == 0 && result.best_move.is_none() {
+            let mut moves = board.collect_legal_moves(false);
+            let best = self
+                .table
+                .probe(position_key(board))
+                .and_then(|entry| entry.best);
+            self.order(board, &mut moves, best);
+            result.best_move = moves.into_iter().next();
+            result.pv.extend(result.best_move.clone());
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    // Best line among the root moves not yet `excluded`, or None when they
+    // have all been searched. Only the unrestricted search goes to the table.
+    fn root_search(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        excluded: &[Ply],
+    ) -> Option<(i32, Vec<Ply>)> {
+        self.nodes += 1;
+
+        let mut moves = board.collect_legal_moves(false);
+        if moves.is_empty() {
+            let current = board.current_player;
+            let score = if board.check_check(current) { -MATE } else { 0 };
+            return Some((score, Vec::new()));
+        }
+
+        moves.retain(|mv| !excluded.contains(mv));
+        if moves.is_empty() {
+            return None;
+        }
+
+        let key = position_key(board);
+        let entry = self.table.probe(key);
+        self.order(board, &mut moves, entry.and_then(|entry| entry.best));
+
+        let mut alpha = -INFINITY;
+        let mut pv = Vec::new();
+        let mut child = Vec::new();
+
+        for mv in moves {
+            make(board, &mv);
+            let score = -self.negamax(board, depth - 1, 1, -INFINITY, -alpha, &mut child);
+            board.undo_last();
+
+            if self.stopped() {
+                return None;
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut child);
+            }
+        }
+
+        if excluded.is_empty() {
+            let first = &pv[0];
+            self.table.store(
+                key,
+                Entry {
+                    depth,
+                    score: alpha,
+                    bound: Bound::Exact,
+                    best: Some((first.from, first.to, promotion_code(board, first))),
+                },
+            );
+        }
+
+        Some((alpha, pv))
+    }
+
+    fn order(&self, board: &Board, moves: &mut [Ply], best: Option<(Point, Point, u8)>) {
+        let id = self.id;
+        let key = |ply: &Ply| -> i32 {
+            if best == Some((ply.from, ply.to, promotion_code(board, ply))) {
+                return 1_000_000;
+            }
+
+            let mut key = 0;
+            if let Some(victim) = board.tiles[ply.to.0][ply.to.1].as_ref() {
+                let attacker = board.tiles[ply.from.0][ply.from.1].as_ref().unwrap();
+                key += 10_000 + 10 * victim.worth - attacker.worth;
+            }
+
+            if ply.promotion.is_some() {
+                key += 9_000 - promotion_code(board, ply) as i32;
+            }
+
+            if key == 0 && id != 0 {
+                key = ((ply.from.0 * 7 + ply.to.1 * 13 + id * 31) % 17) as i32;
+            }
+
+            key
+        };
+
+        moves.sort_by_key(|ply| -key(ply));
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Ply>,
+    ) -> i32 {
+        pv.clear();
+        if self.stopped() {
+            return 0;
+        }
+
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        self.check_time();
+
+        if let Some(probe) = self.tablebase.and_then(|tablebase| tablebase.probe(board)) {
+            let distance = ply as i32 + probe.dtm.unwrap_or(1000) as i32;
+            return match probe.wdl {
+                Wdl::Win => MATE - distance,
+                Wdl::Loss => -MATE + distance,
+                Wdl::Draw => 0,
+            };
+        }
+
+        if let Some(score) = decided(board, ply) {
+            return score;
+        }
+
+        let key = position_key(board);
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry {
+            let score = from_table(entry.score, ply);
+            if ply > 0 && entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut moves = board.collect_legal_moves(false);
+        if moves.is_empty() {
+            let current = board.current_player;
+            return if board.check_check(current) {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        self.order(board, &mut moves, entry.and_then(|entry| entry.best));
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut child = Vec::new();
+
+        for mv in moves {
+            make(board, &mv);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut child);
+            board.undo_last();
+
+            if self.stopped() {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some((mv.from, mv.to, promotion_code(board, &mv)));
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut child);
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.table.store(
+            key,
+            Entry {
+                depth,
+                score: to_table(best, ply),
+                bound,
+                best: best_move,
+            },
+        );
+
+        best
+    }
+
+    fn quiescence(&mut self, board: &mut Board, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.check_time();
+
+        if let Some(score) = decided(board, ply) {
+            return score;
+        }
+
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut moves = board.collect_legal_moves(true);
+        let strongest = board.config.promotions.first().cloned();
+        moves.retain(|mv| mv.promotion.is_none() || mv.promotion == strongest);
+        self.order(board, &mut moves, None);
+
+        for mv in moves {
+            make(board, &mv);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.undo_last();
+
+            if self.stopped() {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        alpha
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+// Time kept in reserve for the GUI or protocol to actually send the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+const MAX_STRETCH: f64 = 3.0;
+
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    started: Instant,
+    soft: Duration,
+    hard: Duration,
+    stretch: f64,
+}
+
+impl TimeManager {
+    pub fn new(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Self {
+        let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let available = remaining.saturating_sub(MOVE_OVERHEAD);
+
+        // With the last move before a time control we may spend nearly all of
+        // it, otherwise the hard limit must leave room for the moves after.
+        let ceiling = if moves_to_go == 1 {
+            available.mul_f64(0.9)
+        } else {
+            available.mul_f64(0.6)
+        };
+
+        let base = available / moves_to_go + increment.mul_f64(0.75);
+        let soft = base.min(ceiling);
+        let hard = (base * 4).min(ceiling);
+
+        Self {
+            started: Instant::now(),
+            soft,
+            hard,
+            stretch: 1.0,
+        }
+    }
+
+    pub fn fixed(time: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            soft: time,
+            hard: time,
+            stretch: 1.0,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.started = Instant::now();
+        self.stretch = 1.0;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn soft_limit(&self) -> Duration {
+        self.soft.mul_f64(self.stretch).min(self.hard)
+    }
+
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    pub fn on_fail_low(&mut self) {
+        self.stretch = (self.stretch * 1.5).min(MAX_STRETCH);
+    }
+
+    pub fn on_best_move_change(&mut self) {
+        self.stretch = (self.stretch * 1.3).min(MAX_STRETCH);
+    }
+
+    pub fn on_stable_iteration(&mut self) {
+        self.stretch = (self.stretch * 0.9).max(1.0);
+    }
+
+    // An iteration that starts after half the soft limit rarely finishes
+    // before it, so it is not worth starting.
+    pub fn can_start_iteration(&self) -> bool {
+        self.elapsed() < self.soft_limit() / 2
+    }
+
+    pub fn hard_expired(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+}
@@ -0,0 +1,196 @@
+use crate::*;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best: Option<(Point, Point, u8)>,
+}
+
+// Every slot stores `key ^ data` next to `data`, so a slot torn by two threads
+// writing at once simply fails the key check instead of returning garbage.
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    pub fn new(entries: usize) -> Self {
+        let mut slots = Vec::with_capacity(entries.max(1));
+        for _ in 0..entries.max(1) {
+            slots.push(Slot {
+                check: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            });
+        }
+
+        Self { slots }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = &self.slots[(key % self.slots.len() as u64) as usize];
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+
+        if data == 0 || check ^ data != key {
+            return None;
+        }
+
+        Some(unpack(data))
+    }
+
+    pub fn store(&self, key: u64, entry: Entry) {
+        let slot = &self.slots[(key % self.slots.len() as u64) as usize];
+        let data = pack(entry);
+
+        slot.check.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+fn pack_point((x, y): Point) -> u64 {
+    (x as u64 & 0xf) | (y as u64 & 0xf) << 4
+}
+
+fn unpack_point(bits: u64) -> Point {
+    ((bits & 0xf) as usize, (bits >> 4 & 0xf) as usize)
+}
+
+// From and to in bits 0-15, a flag for having a best move at 19, the depth
+// at 20-27, the bound at 28-29, the score at 32-47 and the promotion code at
+// 48-55. The top bit keeps a stored entry from ever being 0.
+fn pack(entry: Entry) -> u64 {
+    let mut data = 1 << 63;
+
+    if let Some((from, to, promotion)) = entry.best {
+        data |= pack_point(from) | pack_point(to) << 8 | 1 << 19 | (promotion as u64) << 48;
+    }
+
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+
+    data |= (entry.depth.min(255) as u64) << 20;
+    data |= bound << 28;
+    data |= (entry.score as i16 as u16 as u64) << 32;
+
+    data
+}
+
+fn unpack(data: u64) -> Entry {
+    let best = if data >> 19 & 1 == 1 {
+        Some((
+            unpack_point(data),
+            unpack_point(data >> 8),
+            (data >> 48 & 0xff) as u8,
+        ))
+    } else {
+        None
+    };
+
+    let bound = match data >> 28 & 0x3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    Entry {
+        depth: (data >> 20 & 0xff) as u32,
+        score: (data >> 32) as u16 as i16 as i32,
+        bound,
+        best,
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn name_key(name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash
+}
+
+// Hash of everything the move generator looks at: pieces, their `has_moved`
+// flags, the side to move and a pending en passant target.
+pub fn position_key(board: &Board) -> u64 {
+    let mut key = match board.current_player {
+        Team::White => 0,
+        Team::Black => mix(u64::MAX),
+    };
+
+    for (x, column) in board.tiles.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if let Some(piece) = tile {
+                let team = if piece.team == Team::White { 1 } else { 2 };
+                let moved = if piece.has_moved { 4 } else { 0 };
+                let promoted = if piece.promoted { 8 } else { 0 };
+
+                key ^=
+                    mix(name_key(&piece.name) ^ pack_point((x, y)) << 48 ^ team ^ moved ^ promoted);
+            }
+        }
+    }
+
+    // Each piece in hand by its count, so the order of captures does not
+    // matter.
+    for (team, tag) in [(Team::White, 1 << 40), (Team::Black, 2 << 40)].iter() {
+        let mut names = board.pockets.get(*team).to_vec();
+        names.dedup();
+
+        for name in names {
+            let count = board.pockets.count(*team, &name) as u64;
+            key ^= mix(name_key(&name) ^ tag ^ count << 32 ^ 0x5c);
+        }
+    }
+
+    if board.config.win.check_limit().is_some() {
+        let checks = |team| board.checks_given_by(team) as u64;
+        key ^= mix(0x3c ^ checks(Team::White) << 8 ^ checks(Team::Black) << 16);
+    }
+
+    if let Some((pawn, _)) = board.en_passant_square() {
+        key ^= mix(0xe9 ^ pack_point(pawn) << 8);
+    }
+
+    key
+}
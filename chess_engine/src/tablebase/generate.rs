@@ -0,0 +1,519 @@
+use super::*;
+
+use std::io;
+
+const KING: usize = 0;
+const QUEEN: usize = 1;
+const ROOK: usize = 2;
+const BISHOP: usize = 3;
+const KNIGHT: usize = 4;
+const PAWN: usize = 5;
+
+const STRAIGHT: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const AROUND: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+const JUMPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (-1, 2),
+    (-2, 1),
+    (1, -2),
+    (2, -1),
+    (-1, -2),
+    (-2, -1),
+];
+
+const UNKNOWN: u8 = 0;
+const FINAL_ILLEGAL: u8 = 1;
+const FINAL_WIN: u8 = 2;
+const FINAL_LOSS: u8 = 3;
+const FINAL_DRAW: u8 = 4;
+
+const WIN_FLAG: u32 = 1 << 31;
+
+fn step(square: u8, (dx, dy): (i32, i32)) -> Option<u8> {
+    let x = (square % 8) as i32 + dx;
+    let y = (square / 8) as i32 + dy;
+
+    if !(0..8).contains(&x) || !(0..8).contains(&y) {
+        return None;
+    }
+
+    Some((x + 8 * y) as u8)
+}
+
+// Same direction as `white_stride` in the default configuration.
+fn forward(team: Team) -> i32 {
+    match team {
+        Team::White => -1,
+        Team::Black => 1,
+    }
+}
+
+fn rank(square: u8) -> u8 {
+    square / 8
+}
+
+fn pawn_start(team: Team) -> u8 {
+    match team {
+        Team::White => 6,
+        Team::Black => 1,
+    }
+}
+
+fn last_rank(team: Team) -> u8 {
+    match team {
+        Team::White => 0,
+        Team::Black => 7,
+    }
+}
+
+type Occupancy = [Option<usize>; 64];
+
+fn occupancy(position: &Position) -> Option<Occupancy> {
+    let mut occupied = [None; 64];
+    for (i, (_, _, square)) in position.pieces.iter().enumerate() {
+        if occupied[*square as usize].is_some() {
+            return None;
+        }
+
+        occupied[*square as usize] = Some(i);
+    }
+
+    Some(occupied)
+}
+
+fn directions(kind: usize) -> (&'static [(i32, i32)], bool) {
+    match kind {
+        KING => (&AROUND, false),
+        QUEEN => (&AROUND, true),
+        ROOK => (&STRAIGHT, true),
+        BISHOP => (&DIAGONAL, true),
+        _ => (&JUMPS, false),
+    }
+}
+
+// Squares a non-pawn reaches from `from`, stopping in front of any piece but
+// including the blocking square itself.
+fn reach(kind: usize, from: u8, occupied: &Occupancy) -> Vec<u8> {
+    let (directions, slides) = directions(kind);
+    let mut squares = Vec::new();
+
+    for direction in directions.iter() {
+        let mut current = from;
+        while let Some(next) = step(current, *direction) {
+            squares.push(next);
+            if !slides || occupied[next as usize].is_some() {
+                break;
+            }
+
+            current = next;
+        }
+    }
+
+    squares
+}
+
+fn attacks(position: &Position, occupied: &Occupancy, attacker: usize, target: u8) -> bool {
+    let (kind, team, square) = position.pieces[attacker];
+    if kind == PAWN {
+        return step(square, (1, forward(team))) == Some(target)
+            || step(square, (-1, forward(team))) == Some(target);
+    }
+
+    reach(kind, square, occupied).contains(&target)
+}
+
+fn king_attacked(position: &Position, occupied: &Occupancy, team: Team) -> bool {
+    let king = position
+        .pieces
+        .iter()
+        .find(|(kind, t, _)| *kind == KING && *t == team)
+        .map(|(_, _, square)| *square)
+        .unwrap();
+
+    (0..position.pieces.len())
+        .any(|i| position.pieces[i].1 != team && attacks(position, occupied, i, king))
+}
+
+pub fn is_legal(position: &Position) -> bool {
+    let occupied = match occupancy(position) {
+        Some(occupied) => occupied,
+        None => return false,
+    };
+
+    let pawns_ok = position
+        .pieces
+        .iter()
+        .all(|(kind, _, square)| *kind != PAWN || (rank(*square) != 0 && rank(*square) != 7));
+
+    pawns_ok && !king_attacked(position, &occupied, opposite(position.turn))
+}
+
+// Legal moves as resulting positions. Moves that keep the material are
+// returned with the piece order unchanged so their index stays in this table,
+// captures and promotions come back sorted for probing another table.
+pub fn moves(position: &Position) -> Vec<(Position, bool)> {
+    let occupied = occupancy(position).unwrap();
+    let turn = position.turn;
+    let mut children = Vec::new();
+
+    let mut push = |piece: usize, to: u8, promotion: Option<usize>| {
+        let mut pieces = position.pieces.clone();
+        let captured = occupied[to as usize];
+
+        pieces[piece].2 = to;
+        if let Some(kind) = promotion {
+            pieces[piece].0 = kind;
+        }
+
+        if let Some(captured) = captured {
+            pieces.remove(captured);
+        }
+
+        let exit = captured.is_some() || promotion.is_some();
+        let child = if exit {
+            Position::new(pieces, opposite(turn))
+        } else {
+            Position {
+                pieces,
+                turn: opposite(turn),
+            }
+        };
+
+        if !king_attacked(&child, &occupancy(&child).unwrap(), turn) {
+            children.push((child, exit));
+        }
+    };
+
+    for (i, (kind, team, square)) in position.pieces.iter().enumerate() {
+        if *team != turn {
+            continue;
+        }
+
+        let capturable = |to: u8| match occupied[to as usize] {
+            Some(j) => position.pieces[j].1 != turn && position.pieces[j].0 != KING,
+            None => false,
+        };
+
+        if *kind != PAWN {
+            for to in reach(*kind, *square, &occupied) {
+                if occupied[to as usize].is_none() || capturable(to) {
+                    push(i, to, None);
+                }
+            }
+
+            continue;
+        }
+
+        let mut targets = Vec::new();
+        if let Some(to) = step(*square, (0, forward(turn))) {
+            if occupied[to as usize].is_none() {
+                targets.push(to);
+
+                let double = step(to, (0, forward(turn)));
+                if rank(*square) == pawn_start(turn)
+                    && double.is_some_and(|d| occupied[d as usize].is_none())
+                {
+                    targets.push(double.unwrap());
+                }
+            }
+        }
+
+        for dx in [-1, 1].iter() {
+            if let Some(to) = step(*square, (*dx, forward(turn))) {
+                if capturable(to) {
+                    targets.push(to);
+                }
+            }
+        }
+
+        for to in targets {
+            if rank(to) == last_rank(turn) {
+                for promotion in [QUEEN, ROOK, BISHOP, KNIGHT].iter() {
+                    push(i, to, Some(*promotion));
+                }
+            } else {
+                push(i, to, None);
+            }
+        }
+    }
+
+    children
+}
+
+// Positions one quiet move earlier, with the side that made that move to play.
+fn unmoves(position: &Position) -> Vec<Position> {
+    let occupied = occupancy(position).unwrap();
+    let mover = opposite(position.turn);
+    let mut parents = Vec::new();
+
+    for (i, (kind, team, square)) in position.pieces.iter().enumerate() {
+        if *team != mover {
+            continue;
+        }
+
+        let mut origins = Vec::new();
+        if *kind == PAWN {
+            let back = -forward(mover);
+            if let Some(from) = step(*square, (0, back)) {
+                if occupied[from as usize].is_none() && rank(from) != last_rank(opposite(mover)) {
+                    origins.push(from);
+
+                    let double = step(from, (0, back));
+                    if double.is_some_and(|d| {
+                        rank(d) == pawn_start(mover) && occupied[d as usize].is_none()
+                    }) {
+                        origins.push(double.unwrap());
+                    }
+                }
+            }
+        } else {
+            origins = reach(*kind, *square, &occupied)
+                .into_iter()
+                .filter(|from| occupied[*from as usize].is_none())
+                .collect();
+        }
+
+        for from in origins {
+            let mut pieces = position.pieces.clone();
+            pieces[i].2 = from;
+
+            let parent = Position {
+                pieces,
+                turn: mover,
+            };
+
+            if is_legal(&parent) {
+                parents.push(parent);
+            }
+        }
+    }
+
+    parents
+}
+
+fn worth(material: &Material) -> (i32, i32) {
+    let values = [0, 9, 5, 3, 3, 1];
+    let mut balance = 0;
+    for (kind, team) in material.pieces.iter() {
+        balance += values[*kind] * *team as i32;
+    }
+
+    (
+        balance,
+        material
+            .pieces
+            .iter()
+            .filter(|(_, t)| *t == Team::White)
+            .count() as i32,
+    )
+}
+
+// The orientation tables are stored in: the stronger side plays white.
+pub fn canonical(material: &Material) -> Material {
+    let flipped = material.flipped();
+    if worth(&flipped) > worth(material) {
+        flipped
+    } else {
+        material.clone()
+    }
+}
+
+// Every material a capture or promotion can lead to.
+fn successors(material: &Material) -> Vec<Material> {
+    let mut result = Vec::new();
+
+    for (i, (kind, _)) in material.pieces.iter().enumerate() {
+        if *kind == KING {
+            continue;
+        }
+
+        let mut pieces = material.pieces.clone();
+        pieces.remove(i);
+        result.push(Material::new(pieces));
+
+        if *kind == PAWN {
+            for promotion in [QUEEN, ROOK, BISHOP, KNIGHT].iter() {
+                let mut pieces = material.pieces.clone();
+                pieces[i].0 = *promotion;
+                result.push(Material::new(pieces));
+            }
+        }
+    }
+
+    result
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+// Builds `<name>.dtm` and `<name>.wdl` in `directory` by retrograde analysis,
+// first generating any table a capture or promotion can reach.
+pub fn generate(directory: &Path, name: &str) -> io::Result<()> {
+    let material = Material::parse(name)
+        .filter(|material| material.is_valid())
+        .ok_or_else(|| invalid(format!("{} is not a valid material set", name)))?;
+
+    fs::create_dir_all(directory)?;
+
+    let existing = Tablebase::open(directory);
+    for successor in successors(&material) {
+        if successor.is_insufficient()
+            || existing.table(&successor).is_some()
+            || existing.table(&successor.flipped()).is_some()
+        {
+            continue;
+        }
+
+        generate(directory, &canonical(&successor).name())?;
+    }
+
+    let values = solve(&material, &Tablebase::open(directory));
+
+    let mut wdl = vec![0u8; values.len().div_ceil(4)];
+    for (index, value) in values.iter().enumerate() {
+        let bits = match *value {
+            DRAW => 0,
+            ILLEGAL => 3,
+            v if v >= LOSS => 2,
+            _ => 1,
+        };
+
+        wdl[index / 4] |= bits << (2 * (index % 4));
+    }
+
+    fs::write(directory.join(format!("{}.dtm", material.name())), &values)?;
+    fs::write(directory.join(format!("{}.wdl", material.name())), &wdl)?;
+
+    Ok(())
+}
+
+fn solve(material: &Material, tablebase: &Tablebase) -> Vec<u8> {
+    let size = material.size();
+    let mut state = vec![UNKNOWN; size];
+    let mut plies = vec![0u8; size];
+    let mut remaining = vec![0u8; size];
+    let mut longest_exit = vec![0u8; size];
+    let mut draw_exit = vec![false; size];
+    let mut winning_exit = vec![false; size];
+    let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); 256];
+
+    for index in 0..size {
+        let position = Position::from_index(material, index);
+        if !is_legal(&position) {
+            state[index] = FINAL_ILLEGAL;
+            continue;
+        }
+
+        let children = moves(&position);
+        if children.is_empty() {
+            let occupied = occupancy(&position).unwrap();
+            if king_attacked(&position, &occupied, position.turn) {
+                buckets[0].push(index as u32);
+            } else {
+                state[index] = FINAL_DRAW;
+            }
+
+            continue;
+        }
+
+        let mut fastest_win: Option<u32> = None;
+        for (child, exit) in children {
+            if !exit {
+                remaining[index] += 1;
+                continue;
+            }
+
+            let probe = tablebase.probe_position(&child).unwrap_or(Probe {
+                wdl: Wdl::Draw,
+                dtm: None,
+            });
+
+            let distance = probe.dtm.unwrap_or(0) + 1;
+            match probe.wdl {
+                Wdl::Loss => fastest_win = Some(fastest_win.map_or(distance, |d| d.min(distance))),
+                Wdl::Win => longest_exit[index] = longest_exit[index].max(distance as u8),
+                Wdl::Draw => draw_exit[index] = true,
+            }
+        }
+
+        if let Some(distance) = fastest_win {
+            winning_exit[index] = true;
+            buckets[distance as usize].push(index as u32 | WIN_FLAG);
+        } else if remaining[index] == 0 {
+            if draw_exit[index] {
+                state[index] = FINAL_DRAW;
+            } else {
+                buckets[longest_exit[index] as usize].push(index as u32);
+            }
+        }
+    }
+
+    for ply in 0..buckets.len() {
+        let bucket = std::mem::take(&mut buckets[ply]);
+
+        for entry in bucket {
+            let index = (entry & !WIN_FLAG) as usize;
+            let win = entry & WIN_FLAG != 0;
+            if state[index] != UNKNOWN {
+                continue;
+            }
+
+            state[index] = if win { FINAL_WIN } else { FINAL_LOSS };
+            plies[index] = ply as u8;
+
+            if ply + 1 >= buckets.len() {
+                continue;
+            }
+
+            for parent in unmoves(&Position::from_index(material, index)) {
+                let parent = parent.index();
+                if state[parent] != UNKNOWN {
+                    continue;
+                }
+
+                if !win {
+                    buckets[ply + 1].push(parent as u32 | WIN_FLAG);
+                    continue;
+                }
+
+                if winning_exit[parent] {
+                    continue;
+                }
+
+                remaining[parent] -= 1;
+                if remaining[parent] == 0 {
+                    if draw_exit[parent] {
+                        state[parent] = FINAL_DRAW;
+                    } else {
+                        let distance = (ply + 1).max(longest_exit[parent] as usize);
+                        let last = buckets.len() - 1;
+                        buckets[distance.min(last)].push(parent as u32);
+                    }
+                }
+            }
+        }
+    }
+
+    state
+        .iter()
+        .zip(plies.iter())
+        .map(|(state, plies)| match *state {
+            FINAL_ILLEGAL => ILLEGAL,
+            FINAL_WIN => WIN + plies.min(&(LOSS - WIN - 1)),
+            FINAL_LOSS => LOSS + plies.min(&(u8::MAX - LOSS)),
+            _ => DRAW,
+        })
+        .collect()
+}
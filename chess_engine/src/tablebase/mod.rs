@@ -0,0 +1,348 @@
+pub mod generate;
+
+use crate::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+pub const MAX_PIECES: usize = 4;
+
+// Piece kinds in the order they are listed in a material name like "KBNvK".
+const KINDS: [(char, &str); 6] = [
+    ('K', "King"),
+    ('Q', "Queen"),
+    ('R', "Rook"),
+    ('B', "Bishop"),
+    ('N', "Knight"),
+    ('P', "Pawn"),
+];
+
+const DRAW: u8 = 0;
+const ILLEGAL: u8 = 1;
+const WIN: u8 = 2;
+const LOSS: u8 = 128;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+// Result for the side to move, with the distance to mate in plies when the
+// DTM table is available.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Probe {
+    pub wdl: Wdl,
+    pub dtm: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Material {
+    // Kind index into KINDS and team, white pieces first, each side sorted.
+    pub pieces: Vec<(usize, Team)>,
+}
+
+impl Material {
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut sides = name.split('v');
+        let white = sides.next()?;
+        let black = sides.next()?;
+
+        let mut pieces = Vec::new();
+        for (side, team) in [(white, Team::White), (black, Team::Black)].iter() {
+            for letter in side.chars() {
+                let kind = KINDS.iter().position(|(c, _)| *c == letter)?;
+                pieces.push((kind, *team));
+            }
+        }
+
+        Some(Self::new(pieces))
+    }
+
+    pub fn new(mut pieces: Vec<(usize, Team)>) -> Self {
+        pieces.sort_by_key(|(kind, team)| (*team == Team::Black, *kind));
+        Self { pieces }
+    }
+
+    pub fn name(&self) -> String {
+        let side = |team: Team| -> String {
+            self.pieces
+                .iter()
+                .filter(|(_, t)| *t == team)
+                .map(|(kind, _)| KINDS[*kind].0)
+                .collect()
+        };
+
+        format!("{}v{}", side(Team::White), side(Team::Black))
+    }
+
+    pub fn flipped(&self) -> Self {
+        Self::new(
+            self.pieces
+                .iter()
+                .map(|(kind, team)| (*kind, opposite(*team)))
+                .collect(),
+        )
+    }
+
+    // Exactly one king each and no more than MAX_PIECES pieces.
+    pub fn is_valid(&self) -> bool {
+        let kings = |team: Team| {
+            self.pieces
+                .iter()
+                .filter(|(kind, t)| *kind == 0 && *t == team)
+                .count()
+        };
+
+        self.pieces.len() <= MAX_PIECES && kings(Team::White) == 1 && kings(Team::Black) == 1
+    }
+
+    // Nobody can ever mate with a lone king or king and minor piece.
+    pub fn is_insufficient(&self) -> bool {
+        let others: Vec<usize> = self
+            .pieces
+            .iter()
+            .map(|(kind, _)| *kind)
+            .filter(|kind| *kind != 0)
+            .collect();
+
+        others.is_empty() || (others.len() == 1 && (others[0] == 3 || others[0] == 4))
+    }
+
+    pub fn size(&self) -> usize {
+        2 * 64usize.pow(self.pieces.len() as u32)
+    }
+}
+
+pub fn opposite(team: Team) -> Team {
+    team.opponent()
+}
+
+// Squares are `x + 8 * y` in board coordinates, so a position lines up with
+// `Board::tiles` directly.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Position {
+    pub pieces: Vec<(usize, Team, u8)>,
+    pub turn: Team,
+}
+
+impl Position {
+    pub fn from_board(board: &Board) -> Option<Self> {
+        // The tables only know the standard rules.
+        if board.config.width != 8
+            || board.config.height != 8
+            || board.config.drops
+            || board.config.atomic
+            || board.config.win.name() != "checkmate"
+        {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+
+        for (x, y) in board.enumerate_pieces(|_, _| true) {
+            let piece = board.tiles[x][y].as_ref().unwrap();
+            let kind = KINDS.iter().position(|(_, name)| *name == piece.name)?;
+            if pieces.len() == MAX_PIECES {
+                return None;
+            }
+
+            pieces.push((kind, piece.team, (x + 8 * y) as u8));
+        }
+
+        Some(Self::new(pieces, board.current_player))
+    }
+
+    pub fn new(mut pieces: Vec<(usize, Team, u8)>, turn: Team) -> Self {
+        pieces.sort_by_key(|(kind, team, _)| (*team == Team::Black, *kind));
+        Self { pieces, turn }
+    }
+
+    pub fn material(&self) -> Material {
+        Material::new(
+            self.pieces
+                .iter()
+                .map(|(kind, team, _)| (*kind, *team))
+                .collect(),
+        )
+    }
+
+    // Mirrors the board top to bottom and swaps the colours.
+    pub fn flipped(&self) -> Self {
+        Self::new(
+            self.pieces
+                .iter()
+                .map(|(kind, team, square)| (*kind, opposite(*team), square ^ 56))
+                .collect(),
+            opposite(self.turn),
+        )
+    }
+
+    pub fn index(&self) -> usize {
+        let mut index = 0;
+        for (_, _, square) in self.pieces.iter().rev() {
+            index = index * 64 + *square as usize;
+        }
+
+        index * 2 + if self.turn == Team::White { 0 } else { 1 }
+    }
+
+    pub fn from_index(material: &Material, mut index: usize) -> Self {
+        let turn = if index.is_multiple_of(2) {
+            Team::White
+        } else {
+            Team::Black
+        };
+
+        index /= 2;
+        let mut pieces = Vec::with_capacity(material.pieces.len());
+        for (kind, team) in material.pieces.iter() {
+            pieces.push((*kind, *team, (index % 64) as u8));
+            index /= 64;
+        }
+
+        Self { pieces, turn }
+    }
+}
+
+pub struct Table {
+    data: Vec<u8>,
+    dtm: bool,
+}
+
+impl Table {
+    pub fn load(path: &Path, dtm: bool) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        Some(Self { data, dtm })
+    }
+
+    fn value(&self, index: usize) -> Option<u8> {
+        if self.dtm {
+            return self.data.get(index).copied();
+        }
+
+        let byte = *self.data.get(index / 4)?;
+        Some(byte >> (2 * (index % 4)) & 3)
+    }
+
+    pub fn probe(&self, position: &Position) -> Option<Probe> {
+        let value = self.value(position.index())?;
+        if !self.dtm {
+            let wdl = match value {
+                0 => Wdl::Draw,
+                1 => Wdl::Win,
+                2 => Wdl::Loss,
+                _ => return None,
+            };
+
+            return Some(Probe { wdl, dtm: None });
+        }
+
+        match value {
+            DRAW => Some(Probe {
+                wdl: Wdl::Draw,
+                dtm: None,
+            }),
+            ILLEGAL => None,
+            v if v >= LOSS => Some(Probe {
+                wdl: Wdl::Loss,
+                dtm: Some((v - LOSS) as u32),
+            }),
+            v => Some(Probe {
+                wdl: Wdl::Win,
+                dtm: Some((v - WIN) as u32),
+            }),
+        }
+    }
+}
+
+// Tables are read from `directory` the first time a position needs them.
+pub struct Tablebase {
+    directory: PathBuf,
+    tables: RwLock<HashMap<String, Option<Arc<Table>>>>,
+}
+
+impl Tablebase {
+    pub fn open<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            tables: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn table(&self, material: &Material) -> Option<Arc<Table>> {
+        let name = material.name();
+        if let Some(table) = self.tables.read().unwrap().get(&name) {
+            return table.clone();
+        }
+
+        let table = Table::load(&self.directory.join(format!("{}.dtm", name)), true)
+            .or_else(|| Table::load(&self.directory.join(format!("{}.wdl", name)), false))
+            .map(Arc::new);
+
+        self.tables.write().unwrap().insert(name, table.clone());
+        table
+    }
+
+    pub fn probe_position(&self, position: &Position) -> Option<Probe> {
+        let material = position.material();
+        if !material.is_valid() {
+            return None;
+        }
+
+        if material.is_insufficient() {
+            return Some(Probe {
+                wdl: Wdl::Draw,
+                dtm: None,
+            });
+        }
+
+        if let Some(table) = self.table(&material) {
+            return table.probe(position);
+        }
+
+        let flipped = position.flipped();
+        self.table(&flipped.material())?.probe(&flipped)
+    }
+
+    // Castling rights and en passant are ignored, which only matters in the
+    // rare positions where they are still possible with this little material.
+    pub fn probe(&self, board: &Board) -> Option<Probe> {
+        self.probe_position(&Position::from_board(board)?)
+    }
+
+    // A move that keeps the best result: the fastest mate when winning, the
+    // longest resistance when losing.
+    pub fn best_move(&self, board: &Board) -> Option<Ply> {
+        let current = self.probe(board)?;
+        let mut board = board.clone();
+        let mut best: Option<(Ply, i64)> = None;
+
+        for ply in board.get_legal_moves() {
+            search::make(&mut board, &ply);
+            let probe = self.probe(&board);
+            board.undo_last();
+
+            // A capture or promotion into material without a table on disk
+            // can't be ranked, the other moves still can.
+            let probe = match probe {
+                Some(probe) => probe,
+                None => continue,
+            };
+            let dtm = probe.dtm.unwrap_or(0) as i64;
+            let rank = match (current.wdl, probe.wdl) {
+                (Wdl::Win, Wdl::Loss) => -dtm,
+                (Wdl::Draw, Wdl::Draw) | (Wdl::Loss, Wdl::Win) => dtm,
+                _ => continue,
+            };
+
+            if best.as_ref().is_none_or(|(_, r)| rank > *r) {
+                best = Some((ply, rank));
+            }
+        }
+
+        best.map(|(ply, _)| ply)
+    }
+}
@@ -0,0 +1,355 @@
+use crate::*;
+use pieces::betza;
+use pieces::custom::PieceDefinition;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Error from reading a variant file, `line` counting from 1 or 0 when the
+// problem is not on one line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VariantError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl VariantError {
+    fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+impl fmt::Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl Error for VariantError {}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Value {
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+    List(Vec<String>),
+}
+
+fn parse_string(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    if inner.contains('"') {
+        return None;
+    }
+
+    Some(inner.to_owned())
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner.strip_suffix(']')?.trim();
+        if inner.is_empty() {
+            return Some(Value::List(Vec::new()));
+        }
+
+        return inner
+            .split(',')
+            .map(|item| parse_string(item.trim()))
+            .collect::<Option<Vec<String>>>()
+            .map(Value::List);
+    }
+
+    match text {
+        "true" => Some(Value::Boolean(true)),
+        "false" => Some(Value::Boolean(false)),
+        _ if text.starts_with('"') => parse_string(text).map(Value::Text),
+        _ => text.parse().ok().map(Value::Integer),
+    }
+}
+
+// Drops a `#` comment unless it is inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+// Setting with the line it came from, for error messages.
+struct Entry {
+    line: usize,
+    key: String,
+    value: Value,
+}
+
+impl Entry {
+    fn error(&self, message: &str) -> VariantError {
+        VariantError::new(self.line, format!("{}: {}", self.key, message))
+    }
+
+    fn text(&self) -> Result<String, VariantError> {
+        match &self.value {
+            Value::Text(text) => Ok(text.clone()),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    fn integer(&self) -> Result<i64, VariantError> {
+        match self.value {
+            Value::Integer(value) => Ok(value),
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn size(&self) -> Result<usize, VariantError> {
+        match self.integer()? {
+            value if (3..=16).contains(&value) => Ok(value as usize),
+            _ => Err(self.error("boards are 3 to 16 tiles across")),
+        }
+    }
+
+    fn boolean(&self) -> Result<bool, VariantError> {
+        match self.value {
+            Value::Boolean(value) => Ok(value),
+            _ => Err(self.error("expected true or false")),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>, VariantError> {
+        match &self.value {
+            Value::List(list) => Ok(list.clone()),
+            _ => Err(self.error("expected a list of strings")),
+        }
+    }
+}
+
+// A variant described in a file instead of code. The format is a small part
+// of TOML: `key = value` lines with strings, numbers, booleans and lists of
+// strings, and a `[piece.Name]` section for each custom piece.
+//
+//     name = "Capablanca"
+//     width = 10
+//     fen = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1"
+//     promotions = ["Queen", "Chancellor", "Archbishop", "Rook", "Bishop", "Knight"]
+//
+//     [piece.Archbishop]
+//     letter = "A"
+//     betza = "BN"
+//     worth = 7
+//
+// Other keys are `height`, `castling`, `chess960`, `en_passant`,
+// `double_step`, `drops`, `atomic`, `royal_king`, `forced_captures`,
+// `back_rank_pawns` and `win`.
+#[derive(Clone)]
+pub struct Variant {
+    pub name: String,
+    pub config: BoardConfig,
+    pub fen: Option<String>,
+}
+
+impl Variant {
+    pub fn parse(text: &str) -> Result<Self, VariantError> {
+        let mut settings = Vec::new();
+        let mut pieces: Vec<(usize, String, Vec<Entry>)> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[') {
+                let section = section
+                    .strip_suffix(']')
+                    .ok_or_else(|| VariantError::new(number, "unclosed section".to_owned()))?;
+                let name = section.trim().strip_prefix("piece.").ok_or_else(|| {
+                    VariantError::new(number, format!("unknown section [{}]", section))
+                })?;
+
+                pieces.push((number, name.to_owned(), Vec::new()));
+                continue;
+            }
+
+            let (key, value) =
+                line.split_at(line.find('=').ok_or_else(|| {
+                    VariantError::new(number, "expected `key = value`".to_owned())
+                })?);
+
+            let entry = Entry {
+                line: number,
+                key: key.trim().to_owned(),
+                value: parse_value(value[1..].trim()).ok_or_else(|| {
+                    VariantError::new(number, format!("bad value {}", &value[1..]))
+                })?,
+            };
+
+            match pieces.last_mut() {
+                Some((_, _, entries)) => entries.push(entry),
+                None => settings.push(entry),
+            }
+        }
+
+        let mut config = BoardConfig::default();
+        for (line, name, entries) in pieces {
+            config.pieces.push(piece(line, &name, &entries)?);
+        }
+
+        config
+            .promotions
+            .extend(config.pieces.iter().map(|piece| piece.name.clone()));
+
+        let mut variant = Self {
+            name: "Chess".to_owned(),
+            config,
+            fen: None,
+        };
+
+        for entry in settings.iter() {
+            let config = &mut variant.config;
+            match entry.key.as_str() {
+                "name" => variant.name = entry.text()?,
+                "width" => config.width = entry.size()?,
+                "height" => config.height = entry.size()?,
+                "fen" => variant.fen = Some(entry.text()?),
+                "promotions" => config.promotions = entry.list()?,
+                "castling" => config.castling = entry.boolean()?,
+                "chess960" => config.chess960 = entry.boolean()?,
+                "en_passant" => config.pawn_en_passant = entry.boolean()?,
+                "double_step" => config.pawn_double_step = entry.boolean()?,
+                "drops" => config.drops = entry.boolean()?,
+                "atomic" => config.atomic = entry.boolean()?,
+                "royal_king" => config.royal_king = entry.boolean()?,
+                "forced_captures" => config.forced_captures = entry.boolean()?,
+                "back_rank_pawns" => config.back_rank_pawns = entry.boolean()?,
+                "win" => {
+                    let name = entry.text()?;
+                    config.win = win::by_name(&name)
+                        .ok_or_else(|| entry.error(&format!("unknown win condition {}", name)))?;
+                }
+                _ => return Err(entry.error("unknown setting")),
+            }
+        }
+
+        variant.check(&settings)?;
+        Ok(variant)
+    }
+
+    fn check(&mut self, settings: &[Entry]) -> Result<(), VariantError> {
+        let line = |key: &str| {
+            settings
+                .iter()
+                .find(|entry| entry.key == key)
+                .map_or(0, |entry| entry.line)
+        };
+
+        for name in self.config.promotions.iter() {
+            if self.config.create_piece(name, Team::White).is_none() {
+                return Err(VariantError::new(
+                    line("promotions"),
+                    format!("promotions: unknown piece {}", name),
+                ));
+            }
+        }
+
+        let fen = match &self.fen {
+            Some(fen) => fen.clone(),
+            None if self.config.width == 8 && self.config.height == 8 => return Ok(()),
+            None => {
+                return Err(VariantError::new(
+                    0,
+                    "boards other than 8x8 need a starting fen".to_owned(),
+                ))
+            }
+        };
+
+        // The placement replaces the default setup, so `Board::new` with the
+        // configuration starts from the FEN position too.
+        let mut base = self.config.clone();
+        base.place_pawns = Arc::new(|_| {});
+
+        let board = Board::from_fen(&fen, Some(base.clone())).ok_or_else(|| {
+            VariantError::new(
+                line("fen"),
+                format!("fen does not fit a {}x{} board", base.width, base.height),
+            )
+        })?;
+
+        self.config.chess960 = board.config.chess960;
+        self.config.place_pawns = Arc::new(move |tiles| {
+            if let Some(board) = Board::from_fen(&fen, Some(base.clone())) {
+                *tiles = board.tiles;
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, VariantError> {
+        let text = fs::read_to_string(path.as_ref())
+            .map_err(|e| VariantError::new(0, format!("{}: {}", path.as_ref().display(), e)))?;
+
+        Self::parse(&text)
+    }
+
+    // The starting position, with side to move and castling rights from the
+    // FEN when there is one.
+    pub fn board(&self) -> Board {
+        self.fen
+            .as_ref()
+            .and_then(|fen| Board::from_fen(fen, Some(self.config.clone())))
+            .unwrap_or_else(|| Board::new(Some(self.config.clone())))
+    }
+}
+
+fn piece(line: usize, name: &str, entries: &[Entry]) -> Result<PieceDefinition, VariantError> {
+    let mut letter = None;
+    let mut notation = None;
+    let mut worth = 1;
+    let mut royal = false;
+
+    for entry in entries {
+        match entry.key.as_str() {
+            "letter" => {
+                let text = entry.text()?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii_alphabetic() => letter = Some(c),
+                    _ => return Err(entry.error("expected a single letter")),
+                }
+            }
+            "betza" => notation = Some((entry.line, entry.text()?)),
+            "worth" => worth = entry.integer()? as i32,
+            "royal" => royal = entry.boolean()?,
+            _ => return Err(entry.error("unknown piece setting")),
+        }
+    }
+
+    let letter =
+        letter.ok_or_else(|| VariantError::new(line, format!("piece {} needs a letter", name)))?;
+    let (betza_line, notation) =
+        notation.ok_or_else(|| VariantError::new(line, format!("piece {} needs a betza", name)))?;
+
+    let mut piece = PieceDefinition::new(
+        name,
+        letter,
+        worth,
+        betza::parse(&notation)
+            .map_err(|e| VariantError::new(betza_line, format!("betza: {}", e)))?,
+    );
+    piece.royal = royal;
+
+    Ok(piece)
+}
@@ -0,0 +1,210 @@
+use crate::*;
+
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Decision {
+    Win(Team),
+    Draw,
+}
+
+// How a variant ends the game besides checkmate, which every variant keeps.
+// Set it as `BoardConfig::win`.
+pub trait WinCondition: Send + Sync {
+    // The name used for it in variant files.
+    fn name(&self) -> &'static str;
+
+    // Decides the game in the position reached, `board.current_enemy` having
+    // just moved. Called before checkmate is looked at.
+    fn decide(&self, _board: &mut Board) -> Option<Decision> {
+        None
+    }
+
+    // Whether the move just made is allowed, with `board.current_enemy`
+    // having made it. Moves leaving the own king in check never are.
+    fn allows(&self, _board: &mut Board) -> bool {
+        true
+    }
+
+    // The number of checks that wins, if the board has to count them.
+    fn check_limit(&self) -> Option<u32> {
+        None
+    }
+
+    // Whether having less material is better, which turns the evaluation
+    // around.
+    fn losing(&self) -> bool {
+        false
+    }
+}
+
+pub struct Checkmate;
+
+impl WinCondition for Checkmate {
+    fn name(&self) -> &'static str {
+        "checkmate"
+    }
+}
+
+// Giving check three times wins.
+pub struct ThreeCheck;
+
+impl WinCondition for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "three-check"
+    }
+
+    fn decide(&self, board: &mut Board) -> Option<Decision> {
+        let team = board.current_enemy;
+        if board.checks_given_by(team) >= self.check_limit()? {
+            Some(Decision::Win(team))
+        } else {
+            None
+        }
+    }
+
+    fn check_limit(&self) -> Option<u32> {
+        Some(3)
+    }
+}
+
+// Bringing the king to one of the four centre tiles, d4, e4, d5 and e5 on
+// the standard board, wins.
+pub struct KingOfTheHill;
+
+impl WinCondition for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "king-of-the-hill"
+    }
+
+    fn decide(&self, board: &mut Board) -> Option<Decision> {
+        let (width, height) = (board.config.width, board.config.height);
+        let hill = |x: usize, y: usize| {
+            (x == width / 2 || x + 1 == width / 2) && (y == height / 2 || y + 1 == height / 2)
+        };
+
+        let team = board.current_enemy;
+        let king = board
+            .enumerate_pieces(|piece, (x, y)| piece.necessity && piece.team == team && hill(x, y));
+
+        if king.is_empty() {
+            None
+        } else {
+            Some(Decision::Win(team))
+        }
+    }
+}
+
+// The first king on the last rank wins, and no one may give check. When
+// white gets there first black still has one move to draw by following.
+pub struct RacingKings;
+
+impl RacingKings {
+    fn arrived(board: &Board, team: Team) -> bool {
+        !board
+            .enumerate_pieces(|piece, (_, y)| piece.necessity && piece.team == team && y == 0)
+            .is_empty()
+    }
+}
+
+impl WinCondition for RacingKings {
+    fn name(&self) -> &'static str {
+        "racing-kings"
+    }
+
+    fn decide(&self, board: &mut Board) -> Option<Decision> {
+        let white = Self::arrived(board, Team::White);
+        let black = Self::arrived(board, Team::Black);
+
+        match (white, black) {
+            (true, true) => Some(Decision::Draw),
+            (false, true) => Some(Decision::Win(Team::Black)),
+            (true, false) if board.current_player == Team::Black => {
+                let follows = board.get_legal_moves().into_iter().any(|ply| {
+                    ply.to.1 == 0
+                        && board.tiles[ply.from.0][ply.from.1]
+                            .as_ref()
+                            .is_some_and(|piece| piece.necessity)
+                });
+
+                if follows {
+                    None
+                } else {
+                    Some(Decision::Win(Team::White))
+                }
+            }
+            (true, false) => Some(Decision::Win(Team::White)),
+            (false, false) => None,
+        }
+    }
+
+    fn allows(&self, board: &mut Board) -> bool {
+        let team = board.current_player;
+        !board.check_check(team)
+    }
+}
+
+// Running out of pieces or moves wins. Pair it with captures being forced
+// and the king not being royal, as in `BoardConfig::antichess`.
+pub struct Antichess;
+
+impl WinCondition for Antichess {
+    fn name(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn decide(&self, board: &mut Board) -> Option<Decision> {
+        let team = board.current_player;
+        if board.get_selectable().is_empty() || board.get_legal_moves().is_empty() {
+            Some(Decision::Win(team))
+        } else {
+            None
+        }
+    }
+
+    fn losing(&self) -> bool {
+        true
+    }
+}
+
+// The win condition a variant file names.
+pub fn by_name(name: &str) -> Option<Arc<dyn WinCondition>> {
+    let condition: Arc<dyn WinCondition> = match name {
+        "checkmate" => Arc::new(Checkmate),
+        "three-check" => Arc::new(ThreeCheck),
+        "king-of-the-hill" => Arc::new(KingOfTheHill),
+        "racing-kings" => Arc::new(RacingKings),
+        "antichess" => Arc::new(Antichess),
+        _ => return None,
+    };
+
+    Some(condition)
+}
+
+impl Board {
+    pub fn checks_given_by(&self, team: Team) -> u32 {
+        self.checks_given
+            .iter()
+            .filter(|(_, checker)| *checker == team)
+            .count() as u32
+    }
+
+    // Counts the check the move just made gives, when the win condition asks
+    // for it.
+    pub(crate) fn record_check(&mut self) {
+        if self.config.win.check_limit().is_none() {
+            return;
+        }
+
+        let team = self.current_player;
+        if self.check_check(team) {
+            self.checks_given
+                .push((self.history.len(), self.current_enemy));
+        }
+    }
+
+    pub(crate) fn allowed_by_win(&mut self) -> bool {
+        let win = self.config.win.clone();
+        win.allows(self)
+    }
+}
@@ -1,20 +1,31 @@
-use chess_engine::pieces::default::*;
+use chess_engine::clock::{Clock, RealTime, TimeControl};
+use chess_engine::eco::Opening;
+use chess_engine::events::Event;
+use chess_engine::notation;
+use chess_engine::polyglot::Book;
+use chess_engine::result::Termination;
+use chess_engine::tablebase::{Tablebase, Wdl};
+use chess_engine::variant::Variant;
 use chess_engine::Board;
 use chess_engine::Team;
 
 use dialog::DialogBox;
 use ggez;
-use ggez::event::{self, MouseButton};
+use ggez::event::{self, KeyCode, KeyMods, MouseButton};
 use ggez::nalgebra as na;
 use ggez::{graphics, Context, GameResult};
+use std::env;
 use std::path;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const BOARD_OFFSET_X: usize = 10;
 const BOARD_OFFSET_Y: usize = 10;
 const BOARD_WIDTH: usize = 580;
 const BOARD_HEIGHT: usize = 580;
-const TILE_WIDTH: usize = BOARD_WIDTH / 8;
-const TILE_HEIGHT: usize = BOARD_HEIGHT / 8;
+const BOOK_PATH: &str = "./gui/resources/book.bin";
+const TABLEBASE_PATH: &str = "./gui/resources/tablebases";
 
 fn new_tile(
     ctx: &mut ggez::Context,
@@ -33,9 +44,15 @@ fn new_tile(
     .unwrap()
 }
 
-fn draw_grid(ctx: &mut ggez::Context) -> GameResult<()> {
-    for x in 0..8 {
-        for y in 0..8 {
+// Tiles stay square, so the longer side of the board fills the window.
+fn tile_size(board: &Board) -> usize {
+    BOARD_WIDTH.min(BOARD_HEIGHT) / board.config.width.max(board.config.height)
+}
+
+fn draw_grid(ctx: &mut ggez::Context, board: &Board) -> GameResult<()> {
+    let tile_size = tile_size(board);
+    for x in 0..board.config.width {
+        for y in 0..board.config.height {
             let color;
             if (y % 2 == 0 && x % 2 == 0) || (y % 2 == 1 && x % 2 == 1) {
                 color = graphics::Color::from_rgb(177, 228, 185);
@@ -45,10 +62,10 @@ fn draw_grid(ctx: &mut ggez::Context) -> GameResult<()> {
 
             let tile = new_tile(
                 ctx,
-                (x * TILE_WIDTH + BOARD_OFFSET_X) as f32,
-                (y * TILE_HEIGHT + BOARD_OFFSET_Y) as f32,
-                TILE_WIDTH as f32,
-                TILE_HEIGHT as f32,
+                (x * tile_size + BOARD_OFFSET_X) as f32,
+                (y * tile_size + BOARD_OFFSET_Y) as f32,
+                tile_size as f32,
+                tile_size as f32,
                 color,
             );
             graphics::draw(ctx, &tile, (na::Point2::new(0.0, 0.0),))?;
@@ -59,16 +76,17 @@ fn draw_grid(ctx: &mut ggez::Context) -> GameResult<()> {
 
 fn mark_movables(ctx: &mut ggez::Context, board: &mut Board) -> GameResult<()> {
     let moves = board.get_movable();
+    let tile_size = tile_size(board);
 
     if board.held_piece != None {
         for point in moves.iter() {
             let color = graphics::Color::from_rgb(0, 200, 170);
             let tile = new_tile(
                 ctx,
-                (point.0 * TILE_WIDTH + BOARD_OFFSET_X + 15) as f32,
-                (point.1 * TILE_HEIGHT + BOARD_OFFSET_Y + 15) as f32,
-                (TILE_WIDTH - 30) as f32,
-                (TILE_HEIGHT - 30) as f32,
+                (point.0 * tile_size + BOARD_OFFSET_X + 15) as f32,
+                (point.1 * tile_size + BOARD_OFFSET_Y + 15) as f32,
+                (tile_size - 30) as f32,
+                (tile_size - 30) as f32,
                 color,
             );
             graphics::draw(ctx, &tile, (na::Point2::new(0.0, 0.0),))?;
@@ -89,22 +107,37 @@ fn draw_piece(ctx: &mut ggez::Context, x: usize, y: usize, board: &mut Board) {
             image_path = image_path + "Black.png";
         }
 
-        let image = graphics::Image::new(ctx, image_path).unwrap();
-        graphics::draw(
-            ctx,
-            &image,
-            (na::Point2::new(
-                (x * TILE_WIDTH + BOARD_OFFSET_X + 5) as f32,
-                (y * TILE_HEIGHT + BOARD_OFFSET_Y + 5) as f32,
-            ),),
-        )
-        .unwrap();
+        let position = na::Point2::new(
+            (x * tile_size(board) + BOARD_OFFSET_X + 5) as f32,
+            (y * tile_size(board) + BOARD_OFFSET_Y + 5) as f32,
+        );
+
+        // Custom pieces without an image are drawn as their letter.
+        match graphics::Image::new(ctx, image_path) {
+            Ok(image) => graphics::draw(ctx, &image, (position,)).unwrap(),
+            Err(_) => {
+                let letter = board.config.piece_letter(&name).unwrap_or('?');
+                let letter = if board.is_team((x, y), Team::White) {
+                    letter
+                } else {
+                    letter.to_ascii_lowercase()
+                };
+
+                display_text(
+                    ctx,
+                    &letter.to_string(),
+                    position.x + 10.0,
+                    position.y,
+                    40.0,
+                );
+            }
+        }
     }
 }
 
 fn add_pieces(board: &mut Board, ctx: &mut ggez::Context) -> GameResult<()> {
-    for x in 0..8 {
-        for y in 0..8 {
+    for x in 0..board.config.width {
+        for y in 0..board.config.height {
             draw_piece(ctx, x, y, board);
         }
     }
@@ -117,7 +150,7 @@ fn display_text(ctx: &mut ggez::Context, txt: &str, x: f32, y: f32, font_size: f
     graphics::draw(ctx, &text, (na::Point2::new((x) as f32, (y) as f32),)).unwrap();
 }
 
-fn display_state(ctx: &mut ggez::Context, board: &mut Board) {
+fn display_state(ctx: &mut ggez::Context, board: &mut Board, opening: Option<&Opening>) {
     let current_player_message: &str;
     match board.current_player {
         Team::White => current_player_message = "Current player: White",
@@ -132,18 +165,90 @@ fn display_state(ctx: &mut ggez::Context, board: &mut Board) {
     }
     display_text(ctx, check_message, 600.0, 60.0, 15.0);
 
+    if let Some(clock) = &board.clock {
+        for (i, team) in [Team::White, Team::Black].iter().enumerate() {
+            let label = if *team == Team::White {
+                "White"
+            } else {
+                "Black"
+            };
+            let message = format!("{} clock: {}", label, format_time(clock.remaining(*team)));
+            display_text(ctx, &message, 600.0, 200.0 + 20.0 * i as f32, 15.0);
+        }
+    }
+
+    if let Some(opening) = opening {
+        display_text(ctx, &opening.to_string(), 600.0, 260.0, 15.0);
+    }
+
+    if board.config.drops {
+        for (i, team) in [Team::White, Team::Black].iter().enumerate() {
+            let pocket: Vec<String> = board
+                .pockets
+                .get(*team)
+                .iter()
+                .map(|name| board.config.piece_letter(name).unwrap_or('?').to_string())
+                .collect();
+            let label = if *team == Team::White {
+                "White"
+            } else {
+                "Black"
+            };
+            let message = format!("{} pocket: {}", label, pocket.join(" "));
+            display_text(ctx, &message, 600.0, 140.0 + 20.0 * i as f32, 15.0);
+        }
+    }
+
     if board.finished {
         graphics::clear(ctx, [0.0, 0.0, 0.0, 0.2].into());
         let winner_txt: &str;
         match board.winner {
             Some(Team::Black) => winner_txt = "Black wins!",
             Some(Team::White) => winner_txt = "White wins!",
-            _ => winner_txt = "Stalemate",
+            _ => winner_txt = "Draw",
         }
         display_text(ctx, winner_txt, 200.0, 200.0, 100.0);
+
+        if let Some(result) = board.result {
+            let reason = match result.termination {
+                Termination::Checkmate => "checkmate",
+                Termination::Stalemate => "stalemate",
+                Termination::Resignation => "resignation",
+                Termination::Timeout => "time",
+                Termination::Agreement => "agreement",
+                Termination::Repetition => "repetition",
+                Termination::FiftyMoves => "fifty moves",
+                Termination::InsufficientMaterial => "insufficient material",
+                Termination::Abandonment => "abandonment",
+                Termination::Variant => "variant rules",
+            };
+            display_text(ctx, &format!("By {}", reason), 200.0, 320.0, 40.0);
+        }
     }
 }
 
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn display_tablebase(ctx: &mut ggez::Context, board: &Board, tablebase: &Tablebase) {
+    let message = match tablebase.probe(board) {
+        Some(probe) => {
+            let moves = probe.dtm.map(|dtm| (dtm + 1) / 2);
+            match (probe.wdl, moves) {
+                (Wdl::Draw, _) => "Tablebase: draw".to_owned(),
+                (Wdl::Win, Some(moves)) => format!("Tablebase: mate in {}", moves),
+                (Wdl::Loss, Some(moves)) => format!("Tablebase: mated in {}", moves),
+                (Wdl::Win, None) => "Tablebase: win".to_owned(),
+                (Wdl::Loss, None) => "Tablebase: loss".to_owned(),
+            }
+        }
+        None => "Tablebase: no result".to_owned(),
+    };
+    display_text(ctx, &message, 600.0, 100.0, 15.0);
+}
+
 fn place_piece(board: &mut Board, x: i64, y: i64) {
     if x != -1 && y != -1 {
         let moves = board.get_movable();
@@ -167,51 +272,105 @@ fn place_piece(board: &mut Board, x: i64, y: i64) {
     }
 }
 
-fn promote(board: &mut Board) {
-    let name =
-        dialog::Input::new("Promote to: Queen (Q, q), Knight (N, n), Rook (R, r), Bishop (B, b)")
-            .title("Promotion")
-            .show()
-            .expect("Could not display dialog box")
-            .unwrap();
-    if name == "Q" || name == "q" {
-        board.promote(queen(board.current_enemy));
-        return;
-    } else if name == "N" || name == "n" {
-        board.promote(knight(board.current_enemy));
+fn play_book_move(board: &mut Board, book: Option<&Book>) {
+    if board.finished || book.is_none() {
         return;
-    } else if name == "R" || name == "r" {
-        board.promote(rook(board.current_enemy));
-        return;
-    } else if name == "B" || name == "b" {
-        board.promote(bishop(board.current_enemy));
+    }
+
+    if let Some(ply) = book.unwrap().pick_random(board) {
+        board.play(&ply);
+    }
+}
+
+// Asks for a drop like "N@f3" and plays it if it is legal.
+fn drop_piece(board: &mut Board) {
+    if !board.config.drops || board.finished {
         return;
     }
-    promote(board);
+
+    let san = dialog::Input::new("Drop (e.g. N@f3 or @e4):")
+        .title("Drop")
+        .show()
+        .expect("Could not display dialog box");
+
+    if let Some(ply) = san.and_then(|san| notation::parse_san(board, &san)) {
+        if ply.drop.is_some() {
+            board.play(&ply);
+        }
+    }
 }
 
-fn coordinates_to_tile(x: f32, y: f32) -> (i64, i64) {
+fn promote(board: &mut Board) {
+    let choices: Vec<String> = board
+        .config
+        .promotions
+        .iter()
+        .map(|name| {
+            let letter = board.config.piece_letter(name).unwrap_or('?');
+            format!("{} ({}, {})", name, letter, letter.to_ascii_lowercase())
+        })
+        .collect();
+
+    let name = dialog::Input::new(format!("Promote to: {}", choices.join(", ")))
+        .title("Promotion")
+        .show()
+        .expect("Could not display dialog box")
+        .unwrap();
+
+    let piece = name
+        .chars()
+        .next()
+        .and_then(|letter| board.config.piece_name(letter))
+        .filter(|name| board.config.promotions.contains(name))
+        .and_then(|name| board.config.create_piece(&name, board.current_enemy));
+
+    match piece {
+        Some(piece) => board.promote(piece),
+        None => promote(board),
+    }
+}
+
+fn coordinates_to_tile(x: f32, y: f32, board: &Board) -> (i64, i64) {
+    let tile_size = tile_size(board);
     if x < BOARD_OFFSET_X as f32
         || y < BOARD_OFFSET_Y as f32
-        || x > (BOARD_OFFSET_X as f32 + BOARD_WIDTH as f32)
-        || y > (BOARD_OFFSET_Y as f32 + BOARD_HEIGHT as f32)
+        || x >= (BOARD_OFFSET_X + tile_size * board.config.width) as f32
+        || y >= (BOARD_OFFSET_Y + tile_size * board.config.height) as f32
     {
         return (-1, -1);
     }
 
-    let x1 = (x as usize - BOARD_OFFSET_X) / TILE_WIDTH;
-    let y1 = (y as usize - BOARD_OFFSET_Y) / TILE_HEIGHT;
+    let x1 = (x as usize - BOARD_OFFSET_X) / tile_size;
+    let y1 = (y as usize - BOARD_OFFSET_Y) / tile_size;
     (x1 as i64, y1 as i64)
 }
 
 struct MainState {
     board: Board,
+    book: Option<Book>,
+    tablebase: Tablebase,
+    show_tablebase: bool,
+    // The opening of the game so far, found again after every move or undo
+    // rather than on every frame.
+    opening: Arc<Mutex<Option<&'static Opening>>>,
 }
 
 impl MainState {
-    fn new() -> GameResult<MainState> {
+    fn new(mut board: Board) -> GameResult<MainState> {
+        let opening = Arc::new(Mutex::new(board.opening()));
+        let cached = opening.clone();
+        board.subscribe(move |board, event| {
+            if let Event::Move { .. } | Event::Undo { .. } = event {
+                *cached.lock().unwrap() = board.opening();
+            }
+        });
+
         let s = MainState {
-            board: Board::new(None),
+            board,
+            book: Book::load(BOOK_PATH).ok(),
+            tablebase: Tablebase::open(TABLEBASE_PATH),
+            show_tablebase: false,
+            opening,
         };
         Ok(s)
     }
@@ -219,15 +378,22 @@ impl MainState {
 
 impl event::EventHandler for MainState {
     fn update(&mut self, _ctx: &mut ggez::Context) -> GameResult {
+        if !self.board.finished {
+            self.board.check_flag();
+        }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> GameResult {
         graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
-        draw_grid(ctx)?;
+        draw_grid(ctx, &self.board)?;
         mark_movables(ctx, &mut self.board)?;
         add_pieces(&mut self.board, ctx)?;
-        display_state(ctx, &mut self.board);
+        let opening = *self.opening.lock().unwrap();
+        display_state(ctx, &mut self.board, opening);
+        if self.show_tablebase {
+            display_tablebase(ctx, &self.board, &self.tablebase);
+        }
         graphics::present(ctx)?;
         Ok(())
     }
@@ -241,18 +407,76 @@ impl event::EventHandler for MainState {
     ) {
         match _button {
             MouseButton::Left => {
-                let (x, y) = coordinates_to_tile(_x, _y);
+                let (x, y) = coordinates_to_tile(_x, _y, &self.board);
                 place_piece(&mut self.board, x, y);
             }
             _ => (),
         }
     }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        match keycode {
+            KeyCode::Escape => event::quit(ctx),
+            KeyCode::B => play_book_move(&mut self.board, self.book.as_ref()),
+            KeyCode::T => self.show_tablebase = !self.show_tablebase,
+            KeyCode::D => drop_piece(&mut self.board),
+            KeyCode::R => {
+                let team = self.board.current_player;
+                self.board.resign(team);
+            }
+            KeyCode::C => {
+                self.board.claim_draw();
+            }
+            _ => (),
+        }
+    }
+}
+
+// The board from `--variant <file>`, or standard chess without it, with a
+// clock when `--clock <control>` gives a PGN time control like "300+2".
+fn start_board() -> Board {
+    let args: Vec<String> = env::args().collect();
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|i| args.get(i + 1).cloned().unwrap_or_default())
+    };
+
+    let mut board = match arg("--variant") {
+        Some(path) => match Variant::load(&path) {
+            Ok(variant) => variant.board(),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => Board::new(None),
+    };
+
+    if let Some(control) = arg("--clock") {
+        match TimeControl::parse(&control) {
+            Some(control) => board.set_clock(Clock::new(control, Arc::new(RealTime::new()))),
+            None => {
+                eprintln!("invalid time control: {}", control);
+                process::exit(1);
+            }
+        }
+    }
+
+    board
 }
 
 pub fn main() -> GameResult {
+    let board = start_board();
     let cb = ggez::ContextBuilder::new("Chess", "ggez")
         .add_resource_path(path::PathBuf::from("./gui/resources"));
     let (ctx, event_loop) = &mut cb.build()?;
-    let state = &mut MainState::new()?;
+    let state = &mut MainState::new(board)?;
     event::run(ctx, event_loop, state)
 }